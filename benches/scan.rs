@@ -0,0 +1,70 @@
+//! `cargo bench --bench scan`, compares `Pattern::push` a byte at a time
+//! with `Pattern::feed` on the patterns of `main.rs`.
+extern crate fwcutter;
+use fwcutter::pattern::Pattern;
+use fwcutter::scan;
+
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+const SIZE: usize = 64 * 1024 * 1024;
+
+fn patterns<'a>() -> [Pattern<'a>; 4] {
+    [
+        Pattern::new(&[0x5a, 0x4f, 0x00, 0x00]),
+        Pattern::new(&[0xf7, 0x06, 0x00, 0x00]),
+        Pattern::new(&[0x01, 0x08, 0x01]).lookback(20),
+        Pattern::new(b"\0").lookback(20).repeats(15 * 16 + 7),
+    ]
+}
+
+/// noise with a few padding runs, like a flash dump
+fn image() -> Vec<u8> {
+    let mut x: u32 = 1;
+    let mut data: Vec<u8> = (0..SIZE)
+        .map(|_| {
+            x = x.wrapping_mul(1103515245).wrapping_add(12345);
+            (x >> 16) as u8
+        })
+        .collect();
+    for at in (0..SIZE).step_by(SIZE / 16) {
+        data[at..at + 4096].iter_mut().for_each(|c| *c = 0);
+    }
+    data
+}
+
+/// what `scan` did before `feed`, where every pattern matched
+fn push(data: &[u8], patterns: &mut [Pattern]) -> BTreeSet<(usize, usize)> {
+    let mut found = BTreeSet::new();
+    for (i, c) in data.iter().enumerate() {
+        for (j, p) in patterns.iter_mut().enumerate() {
+            if let Some(n) = p.push(*c) {
+                found.insert((j, i + 1 - n as usize));
+            }
+        }
+    }
+    found
+}
+
+fn time<T, F: FnMut() -> T>(name: &str, mut f: F) -> (T, Duration) {
+    let start = Instant::now();
+    let r = f();
+    let d = start.elapsed();
+    let mbs = SIZE as f64 / d.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{:<10} {:>8.1?} {:>8.1} MiB/s", name, d, mbs);
+    (r, d)
+}
+
+fn main() {
+    let data = image();
+    let (pushed, slow) = time("push", || push(&data, &mut patterns()));
+    let (matches, fast) = time("feed", || scan::scan(&data, &mut patterns()));
+    time("parallel", || scan::scan_parallel(&data, &mut patterns()));
+    println!(
+        "{:.1}x, {} matches",
+        slow.as_secs_f64() / fast.as_secs_f64(),
+        matches.len()
+    );
+    let found: BTreeSet<(usize, usize)> = matches.iter().map(|m| (m.pattern, m.offset)).collect();
+    assert_eq!(found, pushed);
+}
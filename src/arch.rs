@@ -0,0 +1,403 @@
+use crate::output::Row;
+use serde::Serialize;
+
+/// below this score (per KB) we don't really know
+pub const MIN_SCORE: f64 = 5.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arch {
+    MipsLe,
+    MipsBe,
+    Arm,
+    Thumb,
+    Avr,
+    Pic16,
+    Pic18,
+    I8051,
+}
+
+impl Arch {
+    pub const ALL: &'static [Arch] = &[
+        Arch::MipsLe,
+        Arch::MipsBe,
+        Arch::Arm,
+        Arch::Thumb,
+        Arch::Avr,
+        Arch::Pic16,
+        Arch::Pic18,
+        Arch::I8051,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Arch::MipsLe => "mips (little endian)",
+            Arch::MipsBe => "mips (big endian)",
+            Arch::Arm => "arm",
+            Arch::Thumb => "thumb",
+            Arch::Avr => "avr",
+            Arch::Pic16 => "pic16",
+            Arch::Pic18 => "pic18",
+            Arch::I8051 => "8051",
+        }
+    }
+}
+
+/// An instruction pattern: the `len` bytes at every `step` aligned offset,
+/// read in the given byte order, match when `word & mask == value`.
+struct Signature {
+    arch: Arch,
+    name: &'static str,
+    step: usize,
+    len: usize,
+    big: bool,
+    mask: u32,
+    value: u32,
+    /// how telling a match is, returns and prologues weigh the most
+    weight: f64,
+}
+
+const fn sig(
+    arch: Arch,
+    name: &'static str,
+    (step, len, big): (usize, usize, bool),
+    mask: u32,
+    value: u32,
+    weight: f64,
+) -> Signature {
+    Signature {
+        arch,
+        name,
+        step,
+        len,
+        big,
+        mask,
+        value,
+        weight,
+    }
+}
+
+const W32LE: (usize, usize, bool) = (4, 4, false);
+const W32BE: (usize, usize, bool) = (4, 4, true);
+const W16LE: (usize, usize, bool) = (2, 2, false);
+const BYTE: (usize, usize, bool) = (1, 1, false);
+
+static SIGNATURES: &[Signature] = &[
+    sig(Arch::MipsLe, "jr ra", W32LE, 0xffff_ffff, 0x03e0_0008, 4.0),
+    sig(
+        Arch::MipsLe,
+        "addiu sp, -n",
+        W32LE,
+        0xffff_8000,
+        0x27bd_8000,
+        2.0,
+    ),
+    sig(
+        Arch::MipsLe,
+        "sw ra, n(sp)",
+        W32LE,
+        0xffff_0000,
+        0xafbf_0000,
+        2.0,
+    ),
+    sig(
+        Arch::MipsLe,
+        "lw ra, n(sp)",
+        W32LE,
+        0xffff_0000,
+        0x8fbf_0000,
+        2.0,
+    ),
+    sig(Arch::MipsLe, "lui", W32LE, 0xfc00_0000, 0x3c00_0000, 0.2),
+    sig(Arch::MipsLe, "jal", W32LE, 0xfc00_0000, 0x0c00_0000, 0.2),
+    sig(Arch::MipsBe, "jr ra", W32BE, 0xffff_ffff, 0x03e0_0008, 4.0),
+    sig(
+        Arch::MipsBe,
+        "addiu sp, -n",
+        W32BE,
+        0xffff_8000,
+        0x27bd_8000,
+        2.0,
+    ),
+    sig(
+        Arch::MipsBe,
+        "sw ra, n(sp)",
+        W32BE,
+        0xffff_0000,
+        0xafbf_0000,
+        2.0,
+    ),
+    sig(
+        Arch::MipsBe,
+        "lw ra, n(sp)",
+        W32BE,
+        0xffff_0000,
+        0x8fbf_0000,
+        2.0,
+    ),
+    sig(Arch::MipsBe, "lui", W32BE, 0xfc00_0000, 0x3c00_0000, 0.2),
+    sig(Arch::MipsBe, "jal", W32BE, 0xfc00_0000, 0x0c00_0000, 0.2),
+    sig(Arch::Arm, "bx lr", W32LE, 0xffff_ffff, 0xe12f_ff1e, 4.0),
+    sig(
+        Arch::Arm,
+        "push {.., lr}",
+        W32LE,
+        0xffff_4000,
+        0xe92d_4000,
+        2.0,
+    ),
+    sig(
+        Arch::Arm,
+        "pop {.., pc}",
+        W32LE,
+        0xffff_8000,
+        0xe8bd_8000,
+        2.0,
+    ),
+    sig(Arch::Arm, "bl", W32LE, 0xff00_0000, 0xeb00_0000, 0.2),
+    sig(
+        Arch::Arm,
+        "always condition",
+        W32LE,
+        0xf000_0000,
+        0xe000_0000,
+        0.1,
+    ),
+    sig(Arch::Thumb, "bx lr", W16LE, 0xffff, 0x4770, 3.0),
+    sig(Arch::Thumb, "push {.., lr}", W16LE, 0xff00, 0xb500, 1.5),
+    sig(Arch::Thumb, "pop {.., pc}", W16LE, 0xff00, 0xbd00, 1.5),
+    sig(Arch::Thumb, "bl prefix", W16LE, 0xf800, 0xf000, 0.2),
+    sig(Arch::Avr, "ret", W16LE, 0xffff, 0x9508, 3.0),
+    sig(Arch::Avr, "reti", W16LE, 0xffff, 0x9518, 2.0),
+    sig(Arch::Avr, "push", W16LE, 0xfe0f, 0x920f, 1.0),
+    sig(Arch::Avr, "pop", W16LE, 0xfe0f, 0x900f, 1.0),
+    sig(Arch::Avr, "call / jmp", W16LE, 0xfe0e, 0x940c, 0.5),
+    sig(Arch::Avr, "rjmp", W16LE, 0xf000, 0xc000, 0.1),
+    sig(Arch::Pic16, "return", W16LE, 0xffff, 0x0008, 3.0),
+    sig(Arch::Pic16, "retlw", W16LE, 0xff00, 0x3400, 0.5),
+    sig(Arch::Pic16, "call", W16LE, 0xf800, 0x2000, 0.5),
+    sig(Arch::Pic16, "goto", W16LE, 0xf800, 0x2800, 0.5),
+    sig(Arch::Pic18, "return", W16LE, 0xfffe, 0x0012, 3.0),
+    sig(Arch::Pic18, "retlw", W16LE, 0xff00, 0x0c00, 0.5),
+    sig(Arch::Pic18, "call", W16LE, 0xfe00, 0xec00, 0.5),
+    sig(Arch::Pic18, "goto", W16LE, 0xff00, 0xef00, 0.5),
+    sig(Arch::Pic18, "movlw", W16LE, 0xff00, 0x0e00, 0.3),
+    sig(Arch::I8051, "ret", BYTE, 0xff, 0x22, 0.3),
+    sig(Arch::I8051, "reti", BYTE, 0xff, 0x32, 0.5),
+    sig(Arch::I8051, "lcall", BYTE, 0xff, 0x12, 0.2),
+    sig(Arch::I8051, "mov dptr, #", BYTE, 0xff, 0x90, 0.2),
+    sig(Arch::I8051, "movx", BYTE, 0xef, 0xe0, 0.2),
+];
+
+/// PIC16 instructions are 14 bits wide, a `return` in anything else (say
+/// the low half of a MIPS `jr ra`) isn't one
+const PIC16_MIN_SHARE: f64 = 0.9;
+
+/// an `ljmp` at the reset vector is as close to a header as 8051 code gets
+const I8051_RESET_BONUS: f64 = 5.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Evidence {
+    pub name: &'static str,
+    pub count: usize,
+    /// what random data of the same size would give
+    pub expected: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Guess {
+    pub arch: Arch,
+    /// weighted matches above chance, per KB
+    pub score: f64,
+    /// share of the total score, 0..1
+    pub confidence: f64,
+    pub evidence: Vec<Evidence>,
+}
+
+impl Row for Guess {
+    const HEADER: &'static [&'static str] = &["arch", "score", "confidence"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.arch.name().to_string(),
+            format!("{:.3}", self.score),
+            format!("{:.3}", self.confidence),
+        ]
+    }
+}
+
+fn read(b: &[u8], big: bool) -> u32 {
+    let mut w = 0;
+    for i in 0..b.len() {
+        let c = if big { b[i] } else { b[b.len() - 1 - i] };
+        w = (w << 8) | c as u32;
+    }
+    w
+}
+
+/// count of matches and of the positions that were looked at, padding
+/// (all zeroes or all ones) doesn't count for either
+fn count(data: &[u8], s: &Signature) -> (usize, usize) {
+    let ones = ((1u64 << (s.len * 8)) - 1) as u32;
+    let mut matches = 0;
+    let mut positions = 0;
+    let mut o = 0;
+    while o + s.len <= data.len() {
+        let w = read(&data[o..(o + s.len)], s.big);
+        if w != 0 && w != ones {
+            positions += 1;
+            if w & s.mask == s.value {
+                matches += 1;
+            }
+        }
+        o += s.step;
+    }
+    (matches, positions)
+}
+
+/// Scores `data` against every architecture we know, best guess first.
+pub fn guess(data: &[u8]) -> Vec<Guess> {
+    let kb = (data.len() as f64 / 1024.0).max(1.0);
+    let mut guesses: Vec<Guess> = Arch::ALL
+        .iter()
+        .map(|arch| Guess {
+            arch: *arch,
+            score: 0.0,
+            confidence: 0.0,
+            evidence: Vec::new(),
+        })
+        .collect();
+
+    for s in SIGNATURES {
+        let (matches, positions) = count(data, s);
+        let expected = positions as f64 / 2f64.powi(s.mask.count_ones() as i32);
+        let g = guesses.iter_mut().find(|g| g.arch == s.arch).unwrap();
+        g.score += s.weight * (matches as f64 - expected).max(0.0) / kb;
+        if matches > 0 {
+            g.evidence.push(Evidence {
+                name: s.name,
+                count: matches,
+                expected,
+            });
+        }
+    }
+
+    let halves = data
+        .chunks_exact(2)
+        .map(|h| read(h, false))
+        .filter(|&h| h != 0 && h != 0xffff);
+    let (narrow, total) = halves.fold((0, 0), |(n, t), h| (n + (h < 0x4000) as usize, t + 1));
+    if total == 0 || (narrow as f64) < PIC16_MIN_SHARE * total as f64 {
+        let g = guesses.iter_mut().find(|g| g.arch == Arch::Pic16).unwrap();
+        g.score = 0.0;
+    }
+
+    if data.len() >= 3 && data[0] == 0x02 && read(&data[1..3], true) < data.len() as u32 {
+        let g = guesses.iter_mut().find(|g| g.arch == Arch::I8051).unwrap();
+        g.score += I8051_RESET_BONUS;
+        g.evidence.push(Evidence {
+            name: "ljmp at reset",
+            count: 1,
+            expected: 0.0,
+        });
+    }
+
+    let total: f64 = guesses.iter().map(|g| g.score).sum();
+    if total > 0.0 {
+        for g in guesses.iter_mut() {
+            g.confidence = g.score / total;
+        }
+    }
+    guesses.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    guesses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n` copies of a function made of `words`, with some filler between
+    fn code(words: &[u32], n: usize, big: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        for i in 0..n {
+            for w in words {
+                match big {
+                    true => data.extend_from_slice(&w.to_be_bytes()),
+                    false => data.extend_from_slice(&w.to_le_bytes()),
+                }
+            }
+            data.extend_from_slice(&(0x1234_5678u32.wrapping_mul(i as u32 + 1)).to_le_bytes());
+        }
+        data
+    }
+
+    static MIPS: &[u32] = &[
+        0x27bd_ffe8,
+        0xafbf_0014,
+        0x3c04_9d00,
+        0x0f40_0010,
+        0x2484_1234,
+        0x8fbf_0014,
+        0x03e0_0008,
+        0x27bd_0018,
+    ];
+
+    #[test]
+    fn mips() {
+        let g = guess(&code(MIPS, 64, false));
+        assert_eq!(g[0].arch, Arch::MipsLe);
+        assert!(g[0].confidence > 0.8);
+        assert!(g[0]
+            .evidence
+            .iter()
+            .any(|e| e.name == "jr ra" && e.count == 64));
+
+        let g = guess(&code(MIPS, 64, true));
+        assert_eq!(g[0].arch, Arch::MipsBe);
+    }
+
+    #[test]
+    fn arm_and_thumb() {
+        let arm = [
+            0xe92d_4010,
+            0xe3a0_0001,
+            0xeb00_0010,
+            0xe8bd_8010,
+            0xe12f_ff1e,
+        ];
+        assert_eq!(guess(&code(&arm, 64, false))[0].arch, Arch::Arm);
+
+        // push {r4, lr}; movs r0, #1; pop {r4, pc}; bx lr, two per word
+        let thumb = [0x2001_b510, 0x4770_bd10];
+        assert_eq!(guess(&code(&thumb, 64, false))[0].arch, Arch::Thumb);
+    }
+
+    #[test]
+    fn avr() {
+        // push r28; ldi r24, 1; pop r28; ret
+        let avr = [0xe081_93cf, 0x9508_91cf];
+        assert_eq!(guess(&code(&avr, 64, false))[0].arch, Arch::Avr);
+    }
+
+    #[test]
+    fn pic16() {
+        // movlw 1; movwf 0x20; call 0x10; retlw 0; return
+        let pic = [0x00a0_3001, 0x3400_2010, 0x0000_0008];
+        let g = guess(&code(&pic, 64, false));
+        // the filler words are too wide for a PIC16
+        assert_eq!(g.iter().find(|g| g.arch == Arch::Pic16).unwrap().score, 0.0);
+
+        let mut data = Vec::new();
+        for _ in 0..64 {
+            pic.iter()
+                .for_each(|w| data.extend_from_slice(&w.to_le_bytes()));
+        }
+        assert_eq!(guess(&data)[0].arch, Arch::Pic16);
+    }
+
+    #[test]
+    fn padding() {
+        let g = guess(&[0; 4096]);
+        assert!(g.iter().all(|g| g.score == 0.0 && g.confidence == 0.0));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::output::Row;
+use crate::strings::{self, Encoding};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// load addresses are assumed to be on a 4k boundary
+pub const ALIGN: u32 = 0x1000;
+/// shorter strings are too common in random data to vote
+pub const MIN_STRING: usize = 6;
+/// where PIC32 images usually live, always scored on top of the voted ones:
+/// program flash and boot flash in KSEG0 and KSEG1, then RAM
+pub static KNOWN_BASES: &[u32] = &[
+    0x9d00_0000,
+    0xbd00_0000,
+    0x9fc0_0000,
+    0xbfc0_0000,
+    0x8000_0000,
+    0xa000_0000,
+];
+/// how many of the most voted bases get a full score
+const RESCORED: usize = 32;
+/// a pointer to an aligned word could be anything, one to a string start
+/// is much more telling
+const CODE_WEIGHT: f64 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    pub const ALL: &'static [Endian] = &[Endian::Little, Endian::Big];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Endian::Little => "little",
+            Endian::Big => "big",
+        }
+    }
+
+    pub fn read(&self, b: &[u8]) -> u32 {
+        let b = [b[0], b[1], b[2], b[3]];
+        match self {
+            Endian::Little => u32::from_le_bytes(b),
+            Endian::Big => u32::from_be_bytes(b),
+        }
+    }
+}
+
+/// every aligned 32-bit word of `data` and its offset
+pub fn words(data: &[u8], endian: Endian) -> impl Iterator<Item = (usize, u32)> + '_ {
+    data.chunks_exact(4)
+        .enumerate()
+        .map(move |(i, w)| (i * 4, endian.read(w)))
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Candidate {
+    pub base: u32,
+    pub endian: Endian,
+    /// words pointing at the start of a string
+    pub strings: usize,
+    /// other words pointing at an aligned offset of the image
+    pub code: usize,
+    pub score: f64,
+}
+
+impl Row for Candidate {
+    const HEADER: &'static [&'static str] = &["base", "endian", "strings", "code", "score"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            format!("{:#010x}", self.base),
+            self.endian.name().to_string(),
+            self.strings.to_string(),
+            self.code.to_string(),
+            format!("{:.2}", self.score),
+        ]
+    }
+}
+
+fn score(data: &[u8], base: u32, endian: Endian, starts: &HashSet<u32>) -> Candidate {
+    let mut c = Candidate {
+        base,
+        endian,
+        strings: 0,
+        code: 0,
+        score: 0.0,
+    };
+    for (_, w) in words(data, endian) {
+        let offset = match w.checked_sub(base) {
+            Some(o) if (o as usize) < data.len() => o,
+            _ => continue,
+        };
+        if starts.contains(&offset) {
+            c.strings += 1;
+        } else if offset % 4 == 0 {
+            c.code += 1;
+        }
+    }
+    c.score = c.strings as f64 + c.code as f64 * CODE_WEIGHT;
+    c
+}
+
+/// Guesses where `data` is loaded, best candidates first.
+///
+/// Every word that could be a pointer to a string votes for the base that
+/// would make it one, the most voted bases and the `KNOWN_BASES` are then
+/// scored on all the words of the image.
+pub fn guess(data: &[u8], top: usize) -> Vec<Candidate> {
+    if data.len() > u32::MAX as usize {
+        return vec![];
+    }
+    let max_base = u32::MAX - data.len() as u32;
+    let starts = string_starts(data);
+    let mut by_page: HashMap<u32, Vec<u32>> = HashMap::new();
+    for s in starts.iter() {
+        by_page.entry(s % ALIGN).or_default().push(*s);
+    }
+    let starts: HashSet<u32> = starts.into_iter().collect();
+
+    let mut candidates = Vec::new();
+    for endian in Endian::ALL {
+        let mut votes: HashMap<u32, usize> = HashMap::new();
+        for (_, w) in words(data, *endian) {
+            // padding and erased flash
+            if w == 0 || w == u32::MAX {
+                continue;
+            }
+            for s in by_page.get(&(w % ALIGN)).into_iter().flatten() {
+                match w.checked_sub(*s) {
+                    Some(base) if base <= max_base => *votes.entry(base).or_insert(0) += 1,
+                    _ => (),
+                }
+            }
+        }
+        let mut voted: Vec<(u32, usize)> = votes.into_iter().collect();
+        voted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut bases: Vec<u32> = voted.iter().take(RESCORED).map(|(b, _)| *b).collect();
+        for b in KNOWN_BASES.iter().filter(|b| **b <= max_base) {
+            if !bases.contains(b) {
+                bases.push(*b);
+            }
+        }
+        candidates.extend(bases.into_iter().map(|b| score(data, b, *endian, &starts)));
+    }
+
+    sort(&mut candidates);
+    candidates.truncate(top);
+    candidates
+}
+
+/// Both byte orders scored at a `base` we already know, best first.
+pub fn at(data: &[u8], base: u32) -> Vec<Candidate> {
+    let starts: HashSet<u32> = string_starts(data).into_iter().collect();
+    let mut candidates: Vec<Candidate> = Endian::ALL
+        .iter()
+        .map(|e| score(data, base, *e, &starts))
+        .collect();
+    sort(&mut candidates);
+    candidates
+}
+
+fn string_starts(data: &[u8]) -> Vec<u32> {
+    strings::find(data, MIN_STRING, &[Encoding::Ascii])
+        .iter()
+        .map(|s| s.offset as u32)
+        .collect()
+}
+
+fn sort(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap()
+            .then(b.strings.cmp(&a.strings))
+            .then(a.base.cmp(&b.base))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(base: u32, endian: Endian) -> Vec<u8> {
+        let mut data = vec![0x55; 0x40];
+        let mut strings = Vec::new();
+        for s in [
+            "hello world",
+            "firmware update",
+            "temperature",
+            "bad checksum",
+        ] {
+            data.push(0);
+            strings.push(data.len() as u32);
+            data.extend_from_slice(s.as_bytes());
+        }
+        data.resize(0x200, 0);
+        for s in strings {
+            let p = base + s;
+            match endian {
+                Endian::Little => data.extend_from_slice(&p.to_le_bytes()),
+                Endian::Big => data.extend_from_slice(&p.to_be_bytes()),
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn little_endian() {
+        let c = guess(&image(0x1234_5000, Endian::Little), 3);
+        assert_eq!(c[0].base, 0x1234_5000);
+        assert_eq!(c[0].endian, Endian::Little);
+        assert_eq!(c[0].strings, 4);
+        assert!(c.len() <= 3);
+    }
+
+    #[test]
+    fn big_endian() {
+        let c = guess(&image(0x9d00_0000, Endian::Big), 5);
+        assert_eq!((c[0].base, c[0].endian), (0x9d00_0000, Endian::Big));
+    }
+
+    #[test]
+    fn known_base() {
+        let data = image(0x9d00_0000, Endian::Big);
+        let c = at(&data, 0x9d00_0000);
+        assert_eq!(c.len(), 2);
+        assert_eq!((c[0].endian, c[0].strings), (Endian::Big, 4));
+        assert_eq!(c[1].strings, 0);
+    }
+
+    #[test]
+    fn nothing_to_go_on() {
+        let c = guess(&[0; 64], 5);
+        assert!(c.iter().all(|c| c.strings == 0));
+    }
+}
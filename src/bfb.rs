@@ -0,0 +1,499 @@
+use crate::error::{Error, Result};
+use crate::output::Row;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The cube has up to three jets, M1xx codes are for the first one, M2xx for
+/// the second and M3xx for the third.
+pub const EXTRUDERS: usize = 3;
+
+/// feed rate used until the job sets one, in mm/min
+pub const DEFAULT_FEED: f64 = 1000.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "action", content = "value", rename_all = "lowercase")]
+pub enum Action {
+    /// M101, starts pushing filament
+    On,
+    /// M102, runs the motor backwards
+    Reverse,
+    /// M103
+    Off,
+    /// M104 S, in °C
+    Temperature(f64),
+    /// M108 S, motor speed in rpm
+    Speed(f64),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Command {
+    /// `^Key:value` lines at the top of the job
+    Header { key: String, value: String },
+    /// G0 / G1, coordinates are absolute and in mm
+    Move {
+        rapid: bool,
+        x: Option<f64>,
+        y: Option<f64>,
+        z: Option<f64>,
+        e: Option<f64>,
+        f: Option<f64>,
+    },
+    /// G4 P, in milliseconds
+    Dwell { ms: f64 },
+    /// M1xx, M2xx and M3xx, `extruder` counts from 1
+    Extruder { extruder: u8, action: Action },
+    /// M227 P S turns automatic retraction on, with how much to prime and
+    /// reverse, M228 turns it off
+    Retraction {
+        enable: bool,
+        prime: Option<f64>,
+        reverse: Option<f64>,
+    },
+    /// anything we don't know about, kept as is
+    Other {
+        code: String,
+        params: Vec<(char, f64)>,
+    },
+}
+
+/// A parsed line and the offset where it starts in the job.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Line {
+    pub offset: usize,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+fn strip_comment(line: &str) -> &str {
+    let line = match line.find(';') {
+        Some(i) => &line[..i],
+        None => line,
+    };
+    match line.find('(') {
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
+
+/// splits `G1 X1.5Y2` into its letter and number words
+fn words(line: &str, offset: usize) -> Result<Vec<(char, f64)>> {
+    let mut words = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if !c.is_ascii_alphabetic() {
+            return Err(Error::Malformed {
+                offset: offset + i,
+                reason: "expected a letter",
+            });
+        }
+        let start = i + c.len_utf8();
+        let mut end = start;
+        while let Some((j, d)) = chars.peek() {
+            if d.is_ascii_digit() || *d == '.' || *d == '-' || *d == '+' {
+                end = j + d.len_utf8();
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        let value = line[start..end].parse().map_err(|_| Error::Malformed {
+            offset: offset + i,
+            reason: "expected a number",
+        })?;
+        words.push((c.to_ascii_uppercase(), value));
+    }
+    Ok(words)
+}
+
+fn param(params: &[(char, f64)], letter: char) -> Option<f64> {
+    params.iter().find(|(c, _)| *c == letter).map(|(_, v)| *v)
+}
+
+fn command(code: char, number: f64, params: Vec<(char, f64)>) -> Command {
+    let n = number as u32;
+    let integer = number.fract() == 0.0 && number >= 0.0;
+    match (code, n) {
+        ('G', 0) | ('G', 1) if integer => Command::Move {
+            rapid: n == 0,
+            x: param(&params, 'X'),
+            y: param(&params, 'Y'),
+            z: param(&params, 'Z'),
+            e: param(&params, 'E'),
+            f: param(&params, 'F'),
+        },
+        ('G', 4) if integer => Command::Dwell {
+            ms: param(&params, 'P').unwrap_or(0.0),
+        },
+        ('M', 227) | ('M', 228) if integer => Command::Retraction {
+            enable: n == 227,
+            prime: param(&params, 'P'),
+            reverse: param(&params, 'S'),
+        },
+        ('M', 101..=399) if integer => {
+            let s = param(&params, 'S');
+            let action = match (n % 100, s) {
+                (1, _) => Some(Action::On),
+                (2, _) => Some(Action::Reverse),
+                (3, _) => Some(Action::Off),
+                (4, Some(s)) => Some(Action::Temperature(s)),
+                (8, Some(s)) => Some(Action::Speed(s)),
+                _ => None,
+            };
+            match action {
+                Some(action) => Command::Extruder {
+                    extruder: (n / 100) as u8,
+                    action,
+                },
+                None => Command::Other {
+                    code: format!("{}{}", code, number),
+                    params,
+                },
+            }
+        }
+        _ => Command::Other {
+            code: format!("{}{}", code, number),
+            params,
+        },
+    }
+}
+
+/// Tokenizes a BFB job, blank and comment only lines are dropped.
+pub fn parse(text: &[u8]) -> Result<Vec<Line>> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for raw in text.split(|c| *c == b'\n') {
+        let line_offset = offset;
+        offset += raw.len() + 1;
+
+        let line = std::str::from_utf8(raw).map_err(|e| Error::Malformed {
+            offset: line_offset + e.valid_up_to(),
+            reason: "not utf-8",
+        })?;
+        let line = line.trim_end_matches('\r');
+
+        if let Some(header) = line.strip_prefix('^') {
+            let (key, value) = match header.find(':') {
+                Some(i) => (&header[..i], &header[(i + 1)..]),
+                None => (header, ""),
+            };
+            lines.push(Line {
+                offset: line_offset,
+                command: Command::Header {
+                    key: key.trim().to_string(),
+                    value: value.trim().to_string(),
+                },
+            });
+            continue;
+        }
+
+        let mut words = words(strip_comment(line), line_offset)?;
+        if words.is_empty() {
+            continue;
+        }
+        let (code, number) = words.remove(0);
+        lines.push(Line {
+            offset: line_offset,
+            command: command(code, number, words),
+        });
+    }
+    Ok(lines)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct BoundingBox {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl BoundingBox {
+    fn add(b: &mut Option<BoundingBox>, p: [f64; 3]) {
+        match b {
+            Some(b) => {
+                for (i, v) in p.iter().enumerate() {
+                    b.min[i] = b.min[i].min(*v);
+                    b.max[i] = b.max[i].max(*v);
+                }
+            }
+            None => *b = Some(BoundingBox { min: p, max: p }),
+        }
+    }
+
+    pub fn size(&self) -> [f64; 3] {
+        [
+            self.max[0] - self.min[0],
+            self.max[1] - self.min[1],
+            self.max[2] - self.min[2],
+        ]
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Stats {
+    pub headers: BTreeMap<String, String>,
+    pub commands: usize,
+    pub moves: usize,
+    /// commands we parsed but don't understand
+    pub unknown: usize,
+    /// distinct heights something was printed at
+    pub layers: usize,
+    /// filament per extruder in mm: the `E` lengths of the moves plus the
+    /// motor turns times the `mm_per_turn` given to `new`. Nobody measured
+    /// how much a turn of the cube's extruders pushes, so without it this is
+    /// `None` as soon as there are turns, which BFB jobs always have
+    pub filament: Option<[f64; EXTRUDERS]>,
+    /// extruder motor turns, rpm times minutes on, for the moves without `E`
+    pub revolutions: [f64; EXTRUDERS],
+    /// of the moves made while a jet was on
+    pub bbox: Option<BoundingBox>,
+    /// estimated print time in seconds, moves at their feed rate plus dwells,
+    /// heating and acceleration aren't accounted for
+    pub time: f64,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Jet {
+    on: bool,
+    rpm: f64,
+}
+
+impl Stats {
+    pub fn new(lines: &[Line], mm_per_turn: Option<f64>) -> Self {
+        let mut stats = Stats {
+            commands: lines.len(),
+            ..Default::default()
+        };
+        let mut position = [0.0f64; 3];
+        let mut e = 0.0;
+        let mut extruded = [0.0f64; EXTRUDERS];
+        let mut feed = DEFAULT_FEED;
+        let mut jets = [Jet::default(); EXTRUDERS];
+        // in microns, so float noise doesn't make new layers
+        let mut heights: BTreeSet<i64> = BTreeSet::new();
+
+        for line in lines {
+            match &line.command {
+                Command::Header { key, value } => {
+                    stats.headers.insert(key.clone(), value.clone());
+                }
+                Command::Move {
+                    x, y, z, e: de, f, ..
+                } => {
+                    stats.moves += 1;
+                    if let Some(f) = f {
+                        if *f > 0.0 {
+                            feed = *f;
+                        }
+                    }
+                    let to = [
+                        x.unwrap_or(position[0]),
+                        y.unwrap_or(position[1]),
+                        z.unwrap_or(position[2]),
+                    ];
+                    let distance = (0..3)
+                        .map(|i| (to[i] - position[i]).powi(2))
+                        .sum::<f64>()
+                        .sqrt();
+                    let minutes = distance / feed;
+                    stats.time += minutes * 60.0;
+
+                    let printing = jets.iter().any(|j| j.on);
+                    if printing {
+                        if let Some(de) = de {
+                            // the job tells us how much goes out, believe it
+                            // over the motor speed
+                            let active = jets.iter().position(|j| j.on).unwrap_or(0);
+                            extruded[active] += (de - e).max(0.0);
+                        } else {
+                            for (i, j) in jets.iter().enumerate().filter(|(_, j)| j.on) {
+                                stats.revolutions[i] += j.rpm * minutes;
+                            }
+                        }
+                        if distance > 0.0 {
+                            heights.insert((to[2] * 1000.0).round() as i64);
+                            BoundingBox::add(&mut stats.bbox, position);
+                            BoundingBox::add(&mut stats.bbox, to);
+                        }
+                    }
+                    if let Some(de) = de {
+                        e = *de;
+                    }
+                    position = to;
+                }
+                Command::Dwell { ms } => stats.time += ms / 1000.0,
+                Command::Extruder { extruder, action } => {
+                    let jet = match jets.get_mut((*extruder as usize).wrapping_sub(1)) {
+                        Some(jet) => jet,
+                        None => continue,
+                    };
+                    match action {
+                        Action::On => jet.on = true,
+                        Action::Off | Action::Reverse => jet.on = false,
+                        Action::Speed(rpm) => jet.rpm = *rpm,
+                        Action::Temperature(_) => (),
+                    }
+                }
+                Command::Retraction { .. } => (),
+                Command::Other { .. } => stats.unknown += 1,
+            }
+        }
+        stats.layers = heights.len();
+        stats.filament = match mm_per_turn {
+            Some(k) => {
+                let mut f = extruded;
+                for (f, r) in f.iter_mut().zip(stats.revolutions.iter()) {
+                    *f += r * k;
+                }
+                Some(f)
+            }
+            None if stats.revolutions.iter().all(|r| *r == 0.0) => Some(extruded),
+            None => None,
+        };
+        stats
+    }
+}
+
+impl Row for Stats {
+    const HEADER: &'static [&'static str] = &[
+        "commands",
+        "moves",
+        "unknown",
+        "layers",
+        "filament1",
+        "filament2",
+        "filament3",
+        "revolutions1",
+        "revolutions2",
+        "revolutions3",
+        "width",
+        "depth",
+        "height",
+        "time",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        let mut row = vec![
+            self.commands.to_string(),
+            self.moves.to_string(),
+            self.unknown.to_string(),
+            self.layers.to_string(),
+        ];
+        match &self.filament {
+            Some(f) => row.extend(f.iter().map(|f| format!("{:.1}", f))),
+            None => row.extend(vec![String::new(); EXTRUDERS]),
+        }
+        row.extend(self.revolutions.iter().map(|r| format!("{:.1}", r)));
+        match &self.bbox {
+            Some(b) => row.extend(b.size().iter().map(|s| format!("{:.2}", s))),
+            None => row.extend(vec![String::new(); 3]),
+        }
+        row.push(format!("{:.0}", self.time));
+        row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(text: &[u8]) -> Vec<Command> {
+        parse(text)
+            .unwrap()
+            .into_iter()
+            .map(|l| l.command)
+            .collect()
+    }
+
+    #[test]
+    fn tokens() {
+        let c = commands(
+            b"^Firmware:V1.14B\r\n; comment\nG1 X1.5Y-2 F3000 (why not)\nM204 S230\n\
+              M108 S30.0\nM227 P400 S400\nG4 P500\nM999\n",
+        );
+        assert_eq!(
+            c,
+            [
+                Command::Header {
+                    key: "Firmware".into(),
+                    value: "V1.14B".into()
+                },
+                Command::Move {
+                    rapid: false,
+                    x: Some(1.5),
+                    y: Some(-2.0),
+                    z: None,
+                    e: None,
+                    f: Some(3000.0)
+                },
+                Command::Extruder {
+                    extruder: 2,
+                    action: Action::Temperature(230.0)
+                },
+                Command::Extruder {
+                    extruder: 1,
+                    action: Action::Speed(30.0)
+                },
+                Command::Retraction {
+                    enable: true,
+                    prime: Some(400.0),
+                    reverse: Some(400.0)
+                },
+                Command::Dwell { ms: 500.0 },
+                Command::Other {
+                    code: "M999".into(),
+                    params: vec![]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        match parse(b"G1 X1\nG1 X1.2.3\n") {
+            Err(Error::Malformed { offset: 9, .. }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        match parse(b"G1 X1\nG1 *\n") {
+            Err(Error::Malformed { offset: 9, .. }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let job = b"^Version:1\n\
+            G1 X0 Y0 Z0.2 F600\n\
+            M108 S10\nM101\n\
+            G1 X10 F600\nG1 Y10\n\
+            M103\nG1 Z0.4\nM101\n\
+            G1 X0\n\
+            M103\nG0 X50 Y50\nG4 P1500\n\
+            G1 Z0.2\nM101\nG1 X60\nM103\n";
+        let lines = parse(job).unwrap();
+        let stats = Stats::new(&lines, None);
+        assert_eq!(stats.headers["Version"], "1");
+        // back down to 0.2 isn't a new layer
+        assert_eq!(stats.layers, 2);
+        assert_eq!(
+            stats.bbox,
+            Some(BoundingBox {
+                min: [0.0, 0.0, 0.2],
+                max: [60.0, 50.0, 0.4]
+            })
+        );
+        // 40mm printed at 600mm/min is 4 seconds, 2/3 of a turn at 10rpm
+        assert!((stats.revolutions[0] - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.revolutions[1..], [0.0; 2]);
+        assert_eq!(stats.filament, None);
+        // three 0.2mm Z moves, 40mm printing, the travel to 50,50 and the dwell
+        let time = 3.0 * 0.02 + 4.0 + 4100f64.sqrt() / 10.0 + 1.5;
+        assert!((stats.time - time).abs() < 1e-9, "{}", stats.time);
+
+        let f = Stats::new(&lines, Some(3.0)).filament.unwrap();
+        assert!((f[0] - 2.0).abs() < 1e-9);
+        assert_eq!(f[1..], [0.0; 2]);
+    }
+}
@@ -0,0 +1,68 @@
+extern crate fwcutter;
+use fwcutter::arch::{self, MIN_SCORE};
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+
+use std::env;
+
+fn classify(filename: &str, verbose: bool, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let guesses = arch::guess(&mmap);
+
+    match format {
+        Format::Json => output::print_json("arch", filename, &guesses),
+        Format::Csv => output::print_csv(filename, &guesses, first),
+        Format::Text => {
+            println!("{}:", filename);
+            if guesses[0].score < MIN_SCORE {
+                println!("  no idea, doesn't look like code we know");
+                return Ok(());
+            }
+            for g in guesses.iter().filter(|g| g.score > 0.0) {
+                println!(
+                    "  {:<20} {:>5.1}% (score {:.2})",
+                    g.arch.name(),
+                    g.confidence * 100.0,
+                    g.score
+                );
+                if verbose {
+                    for e in g.evidence.iter() {
+                        println!(
+                            "    {:<18} {:>7} (chance {:.1})",
+                            e.name, e.count, e.expected
+                        );
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let verbose = match args.iter().position(|a| a == "-v") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    if args.len() < 2 {
+        eprintln!("usage: {} [--json|--csv] [-v] image.bin...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = classify(filename, verbose, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
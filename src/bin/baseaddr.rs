@@ -0,0 +1,64 @@
+extern crate fwcutter;
+use fwcutter::baseaddr;
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+
+use std::env;
+
+const DEFAULT_TOP: usize = 5;
+
+fn guess(filename: &str, top: usize, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let candidates = baseaddr::guess(&mmap, top);
+
+    match format {
+        Format::Json => output::print_json("baseaddr", filename, &candidates),
+        Format::Csv => output::print_csv(filename, &candidates, first),
+        Format::Text => {
+            println!("{}:", filename);
+            for c in candidates.iter() {
+                println!(
+                    "  {:#010x} {:<6} score {:>10.2} ({} string pointers, {} other)",
+                    c.base,
+                    c.endian.name(),
+                    c.score,
+                    c.strings,
+                    c.code
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let mut top = DEFAULT_TOP;
+    if let Some(i) = args.iter().position(|a| a == "--top") {
+        match args.get(i + 1).and_then(|s| s.parse().ok()) {
+            Some(n) if n > 0 => top = n,
+            _ => {
+                eprintln!("--top needs a number of candidates");
+                std::process::exit(2);
+            }
+        }
+        args.drain(i..(i + 2));
+    }
+    if args.len() < 2 {
+        eprintln!("usage: {} [--json|--csv] [--top n] image.bin...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = guess(filename, top, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
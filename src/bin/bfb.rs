@@ -0,0 +1,99 @@
+extern crate fwcutter;
+use fwcutter::bfb::{self, Stats};
+use fwcutter::cube;
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+
+use std::env;
+
+fn duration(seconds: f64) -> String {
+    let s = seconds.round() as u64;
+    format!("{}h{:02}m{:02}s", s / 3600, s / 60 % 60, s % 60)
+}
+
+fn stats(filename: &str, mm_per_turn: Option<f64>, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    // .cube jobs are decrypted on the fly, anything else is taken as G-code
+    let gcode = if filename.ends_with(".cube") {
+        cube::decrypt(&mmap)?
+    } else {
+        mmap.to_vec()
+    };
+    let stats = Stats::new(&bfb::parse(&gcode)?, mm_per_turn);
+
+    match format {
+        Format::Json => output::print_json("bfb", filename, &stats),
+        Format::Csv => output::print_csv(filename, &[stats], first),
+        Format::Text => {
+            println!("{}:", filename);
+            for (key, value) in stats.headers.iter() {
+                println!("  {}: {}", key, value);
+            }
+            println!(
+                "  {} commands, {} moves, {} unknown",
+                stats.commands, stats.moves, stats.unknown
+            );
+            println!("  {} layers", stats.layers);
+            for (i, f) in stats
+                .filament
+                .iter()
+                .flatten()
+                .enumerate()
+                .filter(|(_, f)| **f > 0.0)
+            {
+                println!("  extruder {}: {:.1}mm of filament", i + 1, f);
+            }
+            for (i, r) in stats
+                .revolutions
+                .iter()
+                .enumerate()
+                .filter(|(_, r)| **r > 0.0)
+            {
+                println!("  extruder {}: {:.1} motor turns", i + 1, r);
+            }
+            if let Some(b) = &stats.bbox {
+                let size = b.size();
+                println!(
+                    "  {:.2} x {:.2} x {:.2}mm from ({:.2}, {:.2}, {:.2})",
+                    size[0], size[1], size[2], b.min[0], b.min[1], b.min[2]
+                );
+            }
+            println!("  about {}", duration(stats.time));
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    // how much filament a turn of the extruder motor pushes, not known for
+    // the cube so it has to come from the user
+    let mut mm_per_turn = None;
+    if let Some(i) = args.iter().position(|a| a == "--mm-per-turn") {
+        mm_per_turn = args.get(i + 1).and_then(|s| s.parse::<f64>().ok());
+        args.drain(i..(i + 2).min(args.len()));
+        if mm_per_turn.is_none() {
+            args.truncate(1);
+        }
+    }
+    if args.len() < 2 {
+        eprintln!(
+            "usage: {} [--json|--csv] [--mm-per-turn mm] job.bfb|job.cube...",
+            args[0]
+        );
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = stats(filename, mm_per_turn, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
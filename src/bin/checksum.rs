@@ -0,0 +1,133 @@
+extern crate fwcutter;
+use fwcutter::checksum::{self, Match, MemberChecks};
+use fwcutter::cut;
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+
+use std::env;
+
+/// a range and the value its check should have
+struct Given {
+    start: usize,
+    end: Option<usize>,
+    value: u32,
+}
+
+fn checks(data: &[u8], given: &Option<Given>) -> Result<Vec<Match>> {
+    match given {
+        Some(g) => checksum::identify(data, g.start, g.end.unwrap_or(data.len()), g.value),
+        None => Ok(checksum::search(data)),
+    }
+}
+
+fn analyse(filename: &str, given: &Option<Given>, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let members = cut::split(&mmap)?;
+    // not an archive, a member that was already cut out or a raw image
+    let results: Vec<MemberChecks> = if members.is_empty() {
+        vec![MemberChecks {
+            path: filename.to_string(),
+            offset: 0,
+            matches: checks(&mmap, given)?,
+        }]
+    } else {
+        members
+            .iter()
+            .map(|m| {
+                Ok(MemberChecks {
+                    path: m.path.clone(),
+                    offset: m.offset,
+                    matches: checks(m.data(&mmap), given)?,
+                })
+            })
+            .collect::<Result<_>>()?
+    };
+
+    match format {
+        Format::Json => output::print_json("checksum", filename, &results),
+        Format::Csv => {
+            for (i, r) in results.iter().enumerate() {
+                output::print_csv(&r.path, &r.matches, first && i == 0)?;
+            }
+            Ok(())
+        }
+        Format::Text => {
+            println!("{}:", filename);
+            for r in results.iter().filter(|r| !r.matches.is_empty()) {
+                println!("  {}", r.path);
+                for m in r.matches.iter() {
+                    let at = match m.stored_at {
+                        Some(o) => format!("stored at {:#x} ({})", o, m.endian.name()),
+                        None => format!("({})", m.endian.name()),
+                    };
+                    println!(
+                        "    {:<18} {:#010x}..{:#010x} = {:#010x} {}",
+                        m.algorithm.name(),
+                        m.start,
+                        m.end,
+                        m.value,
+                        at
+                    );
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn usage(name: &str) -> ! {
+    eprintln!(
+        "usage: {} [--json|--csv] [--range start:end --value v] firmware.ar|image...",
+        name
+    );
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let mut range = None;
+    if let Some(i) = args.iter().position(|a| a == "--range") {
+        let r = args.get(i + 1).and_then(|s| {
+            let (start, end) = s.split_once(':')?;
+            let end = match end {
+                "" => None,
+                e => Some(output::number(e)? as usize),
+            };
+            Some((output::number(start)? as usize, end))
+        });
+        match r {
+            Some(r) => range = Some(r),
+            None => usage(&args[0]),
+        }
+        args.drain(i..(i + 2));
+    }
+    let mut value = None;
+    if let Some(i) = args.iter().position(|a| a == "--value") {
+        match args.get(i + 1).and_then(|s| output::number(s)) {
+            Some(v) => value = Some(v),
+            None => usage(&args[0]),
+        }
+        args.drain(i..(i + 2));
+    }
+    let given = match (range, value) {
+        (Some((start, end)), Some(value)) => Some(Given { start, end, value }),
+        (None, None) => None,
+        _ => usage(&args[0]),
+    };
+    if args.len() < 2 {
+        usage(&args[0]);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = analyse(filename, &given, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
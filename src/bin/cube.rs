@@ -0,0 +1,51 @@
+extern crate fwcutter;
+use fwcutter::cube;
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+
+fn decrypt(filename: &str, out: Option<&String>) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let gcode = cube::decrypt(&mmap)?;
+    match out {
+        Some(out) => fs::write(out, gcode)?,
+        None => io::stdout().write_all(&gcode)?,
+    }
+    Ok(())
+}
+
+fn encrypt(filename: &str, out: Option<&String>) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let job = cube::encrypt(&mmap);
+    match out {
+        Some(out) => fs::write(out, job)?,
+        None => io::stdout().write_all(&job)?,
+    }
+    Ok(())
+}
+
+fn usage(name: &str) -> ! {
+    eprintln!("usage: {} decrypt job.cube [job.bfb]", name);
+    eprintln!("       {} encrypt job.bfb [job.cube]", name);
+    std::process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 || args.len() > 4 {
+        usage(&args[0]);
+    }
+
+    let r = match args[1].as_str() {
+        "decrypt" => decrypt(&args[2], args.get(3)),
+        "encrypt" => encrypt(&args[2], args.get(3)),
+        _ => usage(&args[0]),
+    };
+    if let Err(e) = r {
+        eprintln!("{}: {}", args[2], e);
+        std::process::exit(1);
+    }
+}
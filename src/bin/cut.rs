@@ -1,8 +1,9 @@
 extern crate fwcutter;
-use fwcutter::pattern::Pattern;
-
-use memmap::Mmap;
-use std::io::Write;
+use fwcutter::cut;
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::manifest::Manifest;
+use fwcutter::output::{self, Format};
 
 use std::env;
 use std::fs;
@@ -10,77 +11,68 @@ use std::path::Path;
 
 static EXTRACT_PATH: &str = "./extract";
 
-fn get_filename(p: &mut Pattern) -> Option<String> {
-    let b = &p._ro_get();
-    let l = b.len();
-    //                    println!("{:#?}", p);
-    for i in 1..(l - 1) {
-        if b[l - i - 1] == 0 {
-            let s: String = b[(l - i)..]
-                .iter()
-                .map(|c| match c {
-                    b'\\' => '/',
-                    _ => *c as char,
-                })
-                .collect();
-            return Some(s);
+fn cut_fw(filename: &str) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let mut manifest = Manifest::new(filename, &mmap);
+    for m in cut::split(&mmap)?.iter() {
+        // don't let absolute names escape the extract dir
+        let relative = m.relative_path();
+        let path = Path::new(EXTRACT_PATH).join(&relative);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
         }
+        fs::write(&path, m.data(&mmap))?;
+        manifest.add(&relative.to_string_lossy(), m.offset, None, m.data(&mmap));
+        println!("filename: {:#?}", path);
     }
-    None
+    fs::create_dir_all(EXTRACT_PATH)?;
+    manifest.write(Path::new(EXTRACT_PATH))
 }
 
-fn cut_fw(filename: &str) -> std::io::Result<()> {
-    let file = fs::File::open(filename)?;
-    let mmap = unsafe { Mmap::map(&file)? };
-    let mut reader = mmap.chunks(1);
-    let mut p = Pattern::new(b"\0").lookback(40).repeats(15 * 16);
-
-    let mut buffer: Vec<u8> = Vec::with_capacity(1024 * 1024 * 1024);
-    let mut read = 0;
-    let mut last_p = 0;
-    let mut last_n = 0;
-    let mut current_file: Option<fs::File> = None;
-    loop {
-        if let Some(d) = reader.next() {
-            read += 1;
-            if let Some(n) = p.push(d[0]) {
-                if read - n != last_p {
-                    match get_filename(&mut p) {
-                        Some(s) => {
-                            if let Some(ref mut f) = current_file {
-                                //        the_buffer   the_match  the_file  the_padding
-                                let end = buffer.len() - last_n - s.len() - 1;
-                                assert_ne!(buffer[0], b'\0');
-                                f.write_all(&buffer[..end])?
-                            }
-                            buffer.clear();
-                            let path = Path::new(EXTRACT_PATH).join(s);
-                            let dir = path.parent().expect("path is malformed");
-                            fs::create_dir_all(dir).expect("couldn't mkdir");
-
-                            let file = fs::File::create(&path)?;
-                            current_file = Some(file);
+fn list_fw(filename: &str, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let members = cut::split(&mmap)?;
 
-                            println!("filename: {:#?}", path);
-                        }
-                        None => println!("{:010x} NOT FOUND: {:#?}", last_p, p),
-                    }
-                    last_p = read - n;
-                    last_n = n as usize;
-                }
-            } else {
-                buffer.push(d[0]);
+    match format {
+        Format::Json => output::print_json("archive", filename, &members),
+        Format::Csv => output::print_csv(filename, &members, first),
+        Format::Text => {
+            for m in members.iter() {
+                println!("{:#010x} {:>10} {}", m.offset, m.len, m.path);
             }
-        } else {
-            if let Some(ref mut f) = current_file {
-                f.write_all(&buffer)?
-            }
-            return Ok(());
+            Ok(())
         }
     }
 }
 
-fn main() -> std::io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    cut_fw(&args[1])
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let list = match args.iter().position(|a| a == "--list") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    if args.len() < 2 {
+        eprintln!("usage: {} [--list [--json|--csv]] firmware.ar...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        let r = if list {
+            list_fw(filename, format, i == 0)
+        } else {
+            cut_fw(filename)
+        };
+        if let Err(e) = r {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,87 @@
+extern crate fwcutter;
+use fwcutter::diff::{self, Change};
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+
+use std::env;
+
+/// how many ranges we print per file in text mode
+static MAX_RANGES: usize = 8;
+
+fn print_changes(changes: &[Change], indent: &str) {
+    let hex = |a: &[u8]| a.iter().map(|c| format!("{:02x} ", c)).collect::<String>();
+    for c in changes {
+        match c {
+            Change::Added { path, new } => {
+                println!("{}+ {} {} {}", indent, path, new.len, new.sha256)
+            }
+            Change::Removed { path, old } => {
+                println!("{}- {} {} {}", indent, path, old.len, old.sha256)
+            }
+            Change::Changed {
+                path,
+                old,
+                new,
+                ranges,
+                mpfs,
+            } => {
+                println!(
+                    "{}~ {} {} -> {} {} -> {}",
+                    indent, path, old.len, new.len, old.sha256, new.sha256
+                );
+                for r in ranges.iter().take(MAX_RANGES) {
+                    println!(
+                        "{}    {:#010x} {:>6}: {}",
+                        indent,
+                        r.offset,
+                        r.len,
+                        hex(&r.old)
+                    );
+                    println!("{}    {:>17}: {}", indent, "", hex(&r.new));
+                }
+                if ranges.len() > MAX_RANGES {
+                    println!("{}    ... {} more", indent, ranges.len() - MAX_RANGES);
+                }
+                if let Some(files) = mpfs {
+                    print_changes(files, &format!("{}    ", indent));
+                }
+            }
+        }
+    }
+}
+
+fn diff_fw(old: &str, new: &str, format: Format) -> Result<()> {
+    let old_map = Input::open(old)?;
+    let new_map = Input::open(new)?;
+    let diff = diff::archives(&old_map, &new_map)?;
+
+    let name = format!("{} {}", old, new);
+    match format {
+        Format::Json => output::print_json("diff", &name, &diff),
+        Format::Csv => output::print_csv(&name, &diff.changes, true),
+        Format::Text => {
+            print_changes(&diff.changes, "");
+            println!(
+                "{} changed, {} unchanged",
+                diff.changes.len(),
+                diff.unchanged
+            );
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    if args.len() != 3 {
+        eprintln!("usage: {} [--json|--csv] old.ar new.ar", args[0]);
+        std::process::exit(2);
+    }
+
+    if let Err(e) = diff_fw(&args[1], &args[2], format) {
+        eprintln!("{} {}: {}", args[1], args[2], e);
+        std::process::exit(1);
+    }
+}
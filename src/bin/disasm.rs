@@ -0,0 +1,108 @@
+extern crate fwcutter;
+use fwcutter::baseaddr::Endian;
+use fwcutter::error::{Error, Result};
+use fwcutter::input::Input;
+use fwcutter::mips::{self, Flow, Mode};
+use fwcutter::output::{self, Format};
+
+use std::env;
+
+const DEFAULT_COUNT: usize = 32;
+
+struct Options {
+    base: u32,
+    count: usize,
+    mode: Mode,
+    endian: Endian,
+}
+
+fn disasm(filename: &str, offset: usize, options: &Options, format: Format) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    if offset >= mmap.len() {
+        return Err(Error::InvalidOffset {
+            offset,
+            len: mmap.len(),
+        });
+    }
+    let listing = mips::disassemble(
+        &mmap,
+        offset,
+        options.base,
+        options.count,
+        options.mode,
+        options.endian,
+    );
+
+    match format {
+        Format::Json => output::print_json("disasm", filename, &listing),
+        Format::Csv => output::print_csv(filename, &listing, true),
+        Format::Text => {
+            let labels = mips::labels(&listing);
+            for i in listing.iter() {
+                if labels.contains(&i.address) {
+                    println!("loc_{:08x}:", i.address);
+                }
+                println!("{}", i);
+                if i.flow == Flow::Return {
+                    println!();
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn usage(name: &str) -> ! {
+    eprintln!(
+        "usage: {} [--json|--csv] [--base addr] [-n count] [--mips16] [--big] image offset",
+        name
+    );
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let mut options = Options {
+        base: 0,
+        count: DEFAULT_COUNT,
+        mode: Mode::Mips32,
+        endian: Endian::Little,
+    };
+    args.retain(|a| match a.as_str() {
+        "--mips16" => {
+            options.mode = Mode::Mips16;
+            false
+        }
+        "--big" => {
+            options.endian = Endian::Big;
+            false
+        }
+        _ => true,
+    });
+    for flag in ["--base", "-n"] {
+        if let Some(i) = args.iter().position(|a| a == flag) {
+            let value = match args.get(i + 1).and_then(|v| output::number(v)) {
+                Some(v) => v,
+                None => usage(&args[0]),
+            };
+            match flag {
+                "--base" => options.base = value,
+                _ => options.count = value as usize,
+            }
+            args.drain(i..(i + 2));
+        }
+    }
+    if args.len() != 3 {
+        usage(&args[0]);
+    }
+    let offset = match output::number(&args[2]) {
+        Some(o) => o as usize,
+        None => usage(&args[0]),
+    };
+
+    if let Err(e) = disasm(&args[1], offset, &options, format) {
+        eprintln!("{}: {}", args[1], e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,79 @@
+extern crate fwcutter;
+use fwcutter::entropy::{self, Level};
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+
+use std::env;
+
+fn bar(entropy: f64) -> String {
+    "#".repeat((entropy * 40.0).round() as usize)
+}
+
+fn entropy_map(filename: &str, block_size: usize, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let map = entropy::Map::new(&mmap, block_size);
+
+    match format {
+        Format::Json => output::print_json("entropy", filename, &map),
+        Format::Csv => output::print_csv(filename, &map.blocks, first),
+        Format::Text => {
+            println!(
+                "{}: {} blocks of {} bytes",
+                filename,
+                map.blocks.len(),
+                block_size
+            );
+            for r in map.regions.iter() {
+                let level = match r.level {
+                    Level::High => "high (compressed or encrypted?)",
+                    Level::Medium => "medium",
+                    Level::Low => "low (padding or tables?)",
+                };
+                println!(
+                    "{:#010x}-{:#010x} {:.3} {:<40} {}",
+                    r.offset,
+                    r.offset + r.len,
+                    r.mean,
+                    bar(r.mean),
+                    level
+                );
+            }
+            for t in map.transitions.iter() {
+                println!("{:#010x} {:?} -> {:?}", t.offset, t.from, t.to);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let mut block_size = entropy::DEFAULT_BLOCK_SIZE;
+    if let Some(i) = args.iter().position(|a| a == "--block") {
+        match args.get(i + 1).and_then(|s| s.parse().ok()) {
+            Some(n) if n > 0 => block_size = n,
+            _ => {
+                eprintln!("--block needs a size in bytes");
+                std::process::exit(2);
+            }
+        }
+        args.drain(i..(i + 2));
+    }
+    if args.len() < 2 {
+        eprintln!("usage: {} [--json|--csv] [--block size] image...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = entropy_map(filename, block_size, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
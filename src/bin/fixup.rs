@@ -0,0 +1,110 @@
+extern crate fwcutter;
+use fwcutter::checksum::{Check, Fixup};
+use fwcutter::error::Result;
+use fwcutter::output::{self, Format};
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// checks (or rewrites) every described value of `filename`, true when they
+/// were all right
+fn fixup(
+    filename: &str,
+    fixups: &[Fixup],
+    apply: bool,
+    format: Format,
+    first: bool,
+) -> Result<bool> {
+    let mut data = fs::read(filename)?;
+    let mut checks: Vec<Check> = Vec::new();
+    for f in fixups {
+        checks.push(match apply {
+            true => f.apply(&mut data)?,
+            false => f.check(&data)?,
+        });
+    }
+    if apply && checks.iter().any(|c| !c.ok()) {
+        fs::write(filename, &data)?;
+    }
+
+    match format {
+        Format::Json => output::print_json("fixup", filename, &checks)?,
+        Format::Csv => output::print_csv(filename, &checks, first)?,
+        Format::Text => {
+            println!("{}:", filename);
+            for c in checks.iter() {
+                let status = match (c.ok(), apply) {
+                    (true, _) => "ok",
+                    (false, true) => "fixed",
+                    (false, false) => "BAD",
+                };
+                println!(
+                    "  {:<18} {:#010x}..{:#010x} at {:#010x}: stored {:#010x}, computed {:#010x} {}",
+                    c.algorithm.name(),
+                    c.start,
+                    c.end,
+                    c.at,
+                    c.stored,
+                    c.computed,
+                    status
+                );
+            }
+        }
+    }
+    Ok(apply || checks.iter().all(|c| c.ok()))
+}
+
+fn usage(name: &str) -> ! {
+    eprintln!(
+        "usage: {} [--json|--csv] check|apply description image...",
+        name
+    );
+    eprintln!("  description: a file, or e.g. 'algorithm=crc32 range=0:-4 at=-4 endian=little'");
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    if args.len() < 4 {
+        usage(&args[0]);
+    }
+    let apply = match args[1].as_str() {
+        "check" => false,
+        "apply" => true,
+        _ => usage(&args[0]),
+    };
+    let description = match Path::new(&args[2]).is_file() {
+        true => match fs::read_to_string(&args[2]) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("{}: {}", args[2], e);
+                std::process::exit(2);
+            }
+        },
+        false => args[2].clone(),
+    };
+    let fixups = match Fixup::parse_all(&description) {
+        Ok(f) if !f.is_empty() => f,
+        Ok(_) => usage(&args[0]),
+        Err(e) => {
+            eprintln!("{}: {}", args[2], e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut failed = false;
+    for (i, filename) in args[3..].iter().enumerate() {
+        match fixup(filename, &fixups, apply, format, i == 0) {
+            Ok(ok) => failed |= !ok,
+            Err(e) => {
+                eprintln!("{}: {}", filename, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,51 @@
+extern crate fwcutter;
+use fwcutter::error::Result;
+use fwcutter::ihex;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+
+use std::env;
+use std::fs;
+
+fn read_hex(filename: &str, out: Option<&String>, format: Format) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let image = ihex::Image::parse(&mmap)?;
+
+    match format {
+        Format::Json => output::print_json("hex", filename, &image)?,
+        Format::Csv => output::print_csv(filename, &image.segments, true)?,
+        Format::Text => {
+            for s in image.segments.iter() {
+                println!(
+                    "{:#010x}-{:#010x} {:>10} (line at {:#x})",
+                    s.address,
+                    s.end(),
+                    s.data.len(),
+                    s.offset
+                );
+            }
+            if let Some(start) = image.start {
+                println!("start: {:#010x}", start);
+            }
+        }
+    }
+
+    if let Some(out) = out {
+        fs::write(out, image.concat())?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("usage: {} [--json|--csv] image.hex [out.bin]", args[0]);
+        std::process::exit(2);
+    }
+
+    if let Err(e) = read_hex(&args[1], args.get(2), format) {
+        eprintln!("{}: {}", args[1], e);
+        std::process::exit(1);
+    }
+}
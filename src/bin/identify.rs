@@ -0,0 +1,106 @@
+extern crate fwcutter;
+use fwcutter::error::Result;
+use fwcutter::fingerprint::{Database, Release};
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+
+use std::env;
+
+fn identify(filename: &str, db: &Database, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let id = db.identify(&mmap)?;
+
+    match format {
+        Format::Json => output::print_json("identify", filename, &id),
+        Format::Csv => {
+            let differences = id.closest.map(|c| c.differences).unwrap_or_default();
+            output::print_csv(filename, &differences, first)
+        }
+        Format::Text => {
+            match (&id.closest, id.known) {
+                (Some(c), true) => println!(
+                    "{}: {} {}{}",
+                    filename,
+                    c.release.model,
+                    c.release.version,
+                    match c.release.notes.as_str() {
+                        "" => String::new(),
+                        n => format!(" ({})", n),
+                    }
+                ),
+                (Some(c), false) => {
+                    println!(
+                        "{}: unknown, closest is {} {} ({} members in common)",
+                        filename, c.release.model, c.release.version, c.same
+                    );
+                    for d in c.differences.iter() {
+                        println!("  {:<8} {}", d.status.name(), d.path);
+                    }
+                }
+                (None, _) => println!("{}: unknown, sha256 {}", filename, id.sha256),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn record(filename: &str, model: &str, version: &str, notes: &str) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let release = Release::new(&mmap, model, version, notes)?;
+    let members = release.members.len();
+    let path = Database::record(release)?;
+    println!(
+        "{}: recorded as {} {} ({} members) in {}",
+        filename,
+        model,
+        version,
+        members,
+        path.display()
+    );
+    Ok(())
+}
+
+fn usage(name: &str) -> ! {
+    eprintln!("usage: {} [--json|--csv] firmware.ar...", name);
+    eprintln!("       {} --record model version [notes] firmware.ar", name);
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    if args.len() < 2 {
+        usage(&args[0]);
+    }
+
+    if args[1] == "--record" {
+        let (model, version, notes, filename) = match args.len() {
+            5 => (&args[2], &args[3], "", &args[4]),
+            6 => (&args[2], &args[3], args[4].as_str(), &args[5]),
+            _ => usage(&args[0]),
+        };
+        if let Err(e) = record(filename, model, version, notes) {
+            eprintln!("{}: {}", filename, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let db = match Database::load() {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("fingerprints: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = identify(filename, &db, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
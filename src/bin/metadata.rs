@@ -0,0 +1,69 @@
+extern crate fwcutter;
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::metadata::{self, Kind};
+use fwcutter::output::{self, Format};
+
+use std::collections::BTreeSet;
+use std::env;
+
+fn print_set<T: std::fmt::Display>(name: &str, set: &BTreeSet<T>) {
+    if set.is_empty() {
+        return;
+    }
+    println!("  {}:", name);
+    for v in set {
+        println!("    {}", v);
+    }
+}
+
+fn summary(filename: &str, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let summary = metadata::archive(&mmap)?;
+
+    match format {
+        Format::Json => output::print_json("metadata", filename, &summary),
+        Format::Csv => output::print_csv(filename, &summary.members, first),
+        Format::Text => {
+            println!("{}: {} members", filename, summary.members.len());
+            print_set("builds", &summary.builds);
+            print_set("versions", &summary.versions);
+            print_set("dates", &summary.dates);
+            print_set("compilers", &summary.compilers);
+            print_set("models", &summary.models);
+            for m in summary.members.iter().filter(|m| !m.items.is_empty()) {
+                println!("  {}{}", m.path, if m.decoded { " (decoded)" } else { "" });
+                for i in m.items.iter() {
+                    let kind = match i.kind {
+                        Kind::Version => "version",
+                        Kind::Date => "date",
+                        Kind::Compiler => "compiler",
+                        Kind::Model => "model",
+                    };
+                    println!("    {:#010x} {:<8} {}", i.offset, kind, i.value);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    if args.len() < 2 {
+        eprintln!("usage: {} [--json|--csv] firmware.ar...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = summary(filename, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
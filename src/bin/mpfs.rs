@@ -1,25 +1,50 @@
 extern crate fwcutter;
-use fwcutter::maps;
+use fwcutter::error::Result;
+use fwcutter::input::Input;
 use fwcutter::mpfs;
+use fwcutter::output::{self, Format};
 
-use memmap::Mmap;
+use serde::Serialize;
 use std::env;
-use std::fs;
 
-fn read_mpfs(filename: &str) -> std::io::Result<()> {
-    let file = fs::File::open(filename)?;
-    let mmap = unsafe { Mmap::map(&file)? };
-    let mut reader = maps::ReadableMmap::new(mmap);
+#[derive(Serialize)]
+struct Table {
+    header: mpfs::Header,
+    files: Vec<mpfs::FileHeader>,
+}
 
-    let header = mpfs::Header::new(&mut reader)?;
-    println!("{:#?}", header);
-    reader.seek((header.entries * 2) as isize);
-    let file_header = mpfs::FileHeader::new(&mut reader)?;
-    println!("{:#?}", file_header);
-    Ok(())
+fn read_mpfs(filename: &str, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let (header, files) = mpfs::parse(&mmap)?;
+    match format {
+        Format::Json => output::print_json("mpfs", filename, Table { header, files }),
+        Format::Csv => output::print_csv(filename, &files, first),
+        Format::Text => {
+            println!("{:#?}", header);
+            for f in files.iter() {
+                println!("{:#?}", f);
+            }
+            Ok(())
+        }
+    }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    read_mpfs(&args[1]).unwrap();
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    if args.len() < 2 {
+        eprintln!("usage: {} [--json|--csv] image.mpfs...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = read_mpfs(filename, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
 }
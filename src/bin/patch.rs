@@ -0,0 +1,116 @@
+extern crate fwcutter;
+use fwcutter::error::Result;
+use fwcutter::output::{self, Format};
+use fwcutter::patch::{self, Patch, Status};
+
+use std::env;
+use std::fs;
+
+/// applies (or undoes) `patches` to `filename`, false when something was
+/// in the way and nothing got written
+fn patch_file(
+    filename: &str,
+    patches: &[Patch],
+    revert: bool,
+    dry_run: bool,
+    format: Format,
+    first: bool,
+) -> Result<bool> {
+    let mut data = fs::read(filename)?;
+    let outcomes = match dry_run {
+        true => patch::plan(&data, patches, revert)?,
+        false => patch::apply(&mut data, patches, revert)?,
+    };
+    let refused = outcomes.iter().any(|o| o.status == Status::Mismatch);
+    let changed = outcomes.iter().any(|o| o.status == Status::Ready);
+    if !dry_run && !refused && changed {
+        fs::write(filename, &data)?;
+    }
+
+    match format {
+        Format::Json => output::print_json("patch", filename, &outcomes)?,
+        Format::Csv => output::print_csv(filename, &outcomes, first)?,
+        Format::Text => {
+            println!("{}:", filename);
+            for o in outcomes.iter() {
+                let p = &patches[o.patch];
+                let status = match (o.status, dry_run) {
+                    (Status::Ready, true) => "would patch",
+                    (Status::Ready, false) if refused => "not patched",
+                    (Status::Ready, false) if revert => "reverted",
+                    (Status::Ready, false) => "patched",
+                    (Status::Done, _) if revert => "not patched",
+                    (Status::Done, _) => "already patched",
+                    (Status::Mismatch, _) => "MISMATCH",
+                };
+                println!(
+                    "  #{:<3} {:#010x} {:<15} found {}{}",
+                    o.patch,
+                    o.offset,
+                    status,
+                    output::to_hex(&o.found),
+                    p.member
+                        .as_ref()
+                        .map(|m| format!(" (in {})", m))
+                        .unwrap_or_default()
+                );
+            }
+            if refused {
+                println!("  unexpected bytes, nothing written");
+            }
+        }
+    }
+    Ok(!refused)
+}
+
+fn usage(name: &str) -> ! {
+    eprintln!(
+        "usage: {} [--json|--csv] [--dry-run] apply|revert patches.txt firmware.ar|image...",
+        name
+    );
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let dry_run = match args.iter().position(|a| a == "--dry-run" || a == "-n") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    if args.len() < 4 {
+        usage(&args[0]);
+    }
+    let revert = match args[1].as_str() {
+        "apply" => false,
+        "revert" => true,
+        _ => usage(&args[0]),
+    };
+    let patches = match fs::read_to_string(&args[2])
+        .map_err(Into::into)
+        .and_then(|t| Patch::parse_all(&t))
+    {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{}: {}", args[2], e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut failed = false;
+    for (i, filename) in args[3..].iter().enumerate() {
+        match patch_file(filename, &patches, revert, dry_run, format, i == 0) {
+            Ok(ok) => failed |= !ok,
+            Err(e) => {
+                eprintln!("{}: {}", filename, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,75 @@
+extern crate fwcutter;
+use fwcutter::error::Result;
+use fwcutter::ihex;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+use fwcutter::pic32::{self, ConfigWord, Field, Placement};
+
+use serde::Serialize;
+use std::env;
+
+#[derive(Serialize)]
+struct Report {
+    segments: Vec<Placement>,
+    config: Vec<ConfigWord>,
+}
+
+fn decode(filename: &str, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let image = ihex::Image::parse(&mmap)?;
+    let report = Report {
+        segments: pic32::placements(&image),
+        config: pic32::config(&image),
+    };
+
+    match format {
+        Format::Json => output::print_json("pic32", filename, &report),
+        Format::Csv => {
+            let fields: Vec<Field> = report.config.into_iter().flat_map(|w| w.fields).collect();
+            output::print_csv(filename, &fields, first)
+        }
+        Format::Text => {
+            println!("{}:", filename);
+            for s in report.segments.iter() {
+                println!(
+                    "  {:#010x} {:>8} bytes {:<8} {:#010x} {}",
+                    s.address,
+                    s.len,
+                    s.space.name(),
+                    s.physical,
+                    s.region.unwrap_or("?")
+                );
+            }
+            if report.config.is_empty() {
+                println!("  no configuration words");
+            }
+            for w in report.config.iter() {
+                println!("  {} {:#010x} @ {:#010x}", w.name, w.value, w.address);
+                for f in w.fields.iter() {
+                    println!("    {:<10} {:>6} {}", f.name, f.value, f.meaning);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    if args.len() < 2 {
+        eprintln!("usage: {} [--json|--csv] image.hex...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = decode(filename, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,102 @@
+extern crate fwcutter;
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+use fwcutter::signature::{self, Hit};
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// how deep we go into compressed data when carving
+static MAX_DEPTH: usize = 4;
+
+fn print_hits(hits: &[Hit], indent: &str) {
+    for h in hits {
+        let size = h
+            .size
+            .map(|s| format!("{:#x}", s))
+            .unwrap_or_else(|| "?".to_string());
+        println!(
+            "{}{:#010x} {:<10} {:>10} {:.2} {}",
+            indent,
+            h.offset,
+            h.kind.name(),
+            size,
+            h.confidence,
+            h.description
+        );
+    }
+}
+
+/// writes every hit in `dir`, and carves what's inside compressed ones too
+fn carve(data: &[u8], hits: &[Hit], dir: &Path, depth: usize, verbose: bool) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    for h in hits {
+        let name = format!("{:08x}.{}", h.offset, h.kind.extension());
+        let content = h.data(data);
+        fs::write(dir.join(&name), content)?;
+
+        if depth >= MAX_DEPTH {
+            continue;
+        }
+        if let Some(inner) = signature::decompress(h.kind, content) {
+            let inner_dir = dir.join(format!("{}.d", name));
+            fs::create_dir_all(&inner_dir)?;
+            fs::write(inner_dir.join("data"), &inner)?;
+            let inner_hits = signature::scan(&inner);
+            if verbose {
+                print_hits(&inner_hits, &"    ".repeat(depth + 1));
+            }
+            carve(&inner, &inner_hits, &inner_dir, depth + 1, verbose)?;
+        }
+    }
+    Ok(())
+}
+
+fn scan_fw(filename: &str, carve_dir: Option<&String>, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let hits = signature::scan(&mmap);
+
+    match format {
+        Format::Json => output::print_json("signatures", filename, &hits)?,
+        Format::Csv => output::print_csv(filename, &hits, first)?,
+        Format::Text => print_hits(&hits, ""),
+    }
+
+    if let Some(dir) = carve_dir {
+        let name = Path::new(filename).file_name().unwrap_or_default();
+        let verbose = format == Format::Text;
+        carve(&mmap, &hits, &Path::new(dir).join(name), 0, verbose)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let mut carve_dir = None;
+    if let Some(i) = args.iter().position(|a| a == "--carve") {
+        if i + 1 >= args.len() {
+            eprintln!("--carve needs a directory");
+            std::process::exit(2);
+        }
+        carve_dir = Some(args.remove(i + 1));
+        args.remove(i);
+    }
+    if args.len() < 2 {
+        eprintln!("usage: {} [--json|--csv] [--carve dir] image...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = scan_fw(filename, carve_dir.as_ref(), format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,89 @@
+extern crate fwcutter;
+use fwcutter::error::Result;
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+use fwcutter::strings::{self, Encoding, Layout};
+
+use std::env;
+
+fn strings(
+    filename: &str,
+    min: usize,
+    encodings: &[Encoding],
+    format: Format,
+    first: bool,
+) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let mut found = strings::find(&mmap, min, encodings);
+    Layout::new(&mmap).attribute(&mut found);
+
+    match format {
+        Format::Json => output::print_json("strings", filename, &found),
+        Format::Csv => output::print_csv(filename, &found, first),
+        Format::Text => {
+            for s in found.iter() {
+                let owner = match (&s.member, &s.file) {
+                    (Some(m), Some(f)) => format!(" [{}:{}]", m, f),
+                    (Some(m), None) => format!(" [{}]", m),
+                    (None, Some(f)) => format!(" [:{}]", f),
+                    (None, None) => String::new(),
+                };
+                println!(
+                    "{:#010x} {:<7}{} {}",
+                    s.offset,
+                    s.encoding.name(),
+                    owner,
+                    s.value.escape_debug()
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+fn usage(name: &str) -> ! {
+    eprintln!(
+        "usage: {} [--json|--csv] [-n min] [--encoding ascii,utf8,utf16le,utf16be] file...",
+        name
+    );
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let mut min = strings::DEFAULT_MIN_LEN;
+    let mut encodings = Encoding::ALL.to_vec();
+    if let Some(i) = args.iter().position(|a| a == "-n") {
+        match args.get(i + 1).and_then(|s| s.parse().ok()) {
+            Some(n) if n > 0 => min = n,
+            _ => usage(&args[0]),
+        }
+        args.drain(i..(i + 2));
+    }
+    if let Some(i) = args.iter().position(|a| a == "--encoding") {
+        let parsed: Option<Vec<Encoding>> = args
+            .get(i + 1)
+            .map(|s| s.split(',').map(Encoding::from_name).collect())
+            .unwrap_or(None);
+        match parsed {
+            Some(e) => encodings = e,
+            None => usage(&args[0]),
+        }
+        args.drain(i..(i + 2));
+    }
+    if args.len() < 2 {
+        usage(&args[0]);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = strings(filename, min, &encodings, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
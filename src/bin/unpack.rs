@@ -0,0 +1,110 @@
+extern crate fwcutter;
+use fwcutter::error::Result;
+use fwcutter::input::{self, Input};
+use fwcutter::manifest::Manifest;
+use fwcutter::output::{self, Format};
+use fwcutter::unpack::{self, Entry};
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+static EXTRACT_PATH: &str = "./extract";
+/// written next to the tree, every file and where it comes from
+static PROVENANCE: &str = "unpack.json";
+
+fn write_tree(dir: &Path, source: &str, data: &[u8], entries: &[Entry]) -> Result<()> {
+    let mut manifest = Manifest::new(source, data);
+    for e in entries.iter() {
+        let path = dir.join(&e.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, &e.data)?;
+        let step = e.chain.last().unwrap();
+        // members come straight from the source, the rest from `parent.d/`
+        let parent = match e.chain.len() {
+            1 => None,
+            _ => e.path.strip_suffix(&format!(".d/{}", step.name)),
+        };
+        let offset = step.offset;
+        manifest.add(&e.path, offset, parent, &e.data);
+    }
+    manifest.write(dir)?;
+    let record = fs::File::create(dir.join(PROVENANCE))?;
+    serde_json::to_writer_pretty(record, entries).map_err(io::Error::from)?;
+    Ok(())
+}
+
+fn unpack_file(filename: &str, out: &Path, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let name = match filename {
+        input::STDIN => "stdin".to_string(),
+        _ => Path::new(filename)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+    let entries = unpack::unpack(&mmap, &name)?;
+    write_tree(out, filename, &mmap, &entries)?;
+
+    match format {
+        Format::Json => output::print_json("unpack", filename, &entries),
+        Format::Csv => output::print_csv(filename, &entries, first),
+        Format::Text => {
+            println!("{}: {} files in {}", filename, entries.len(), out.display());
+            for e in entries.iter() {
+                let step = e.chain.last().unwrap();
+                println!(
+                    "{:indent$}{} ({}, {} bytes)",
+                    "",
+                    step.name,
+                    step.stage.name(),
+                    e.size,
+                    indent = 2 * e.chain.len()
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let mut out = EXTRACT_PATH.to_string();
+    if let Some(i) = args.iter().position(|a| a == "-o") {
+        match args.get(i + 1) {
+            Some(o) => out = o.clone(),
+            None => {
+                eprintln!("-o needs a directory");
+                std::process::exit(2);
+            }
+        }
+        args.drain(i..(i + 2));
+    }
+    if args.len() < 2 {
+        eprintln!(
+            "usage: {} [--json|--csv] [-o dir] firmware.ar|image...",
+            args[0]
+        );
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        // several inputs get a directory each
+        let dir = match args.len() {
+            2 => Path::new(&out).to_path_buf(),
+            _ => Path::new(&out).join(Path::new(filename).file_name().unwrap_or_default()),
+        };
+        if let Err(e) = unpack_file(filename, &dir, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,55 @@
+extern crate fwcutter;
+use fwcutter::error::Result;
+use fwcutter::manifest::{Manifest, Status};
+use fwcutter::output::{self, Format};
+
+use std::env;
+use std::path::Path;
+
+/// true when every file of the manifest is there, unchanged
+fn verify(dir: &str, format: Format, first: bool) -> Result<bool> {
+    let manifest = Manifest::read(Path::new(dir))?;
+    let checks = manifest.verify(Path::new(dir));
+
+    match format {
+        Format::Json => output::print_json("verify", dir, &checks)?,
+        Format::Csv => output::print_csv(dir, &checks, first)?,
+        Format::Text => {
+            let bad: Vec<_> = checks.iter().filter(|c| c.status != Status::Ok).collect();
+            println!(
+                "{}: {} files from {}, {} ok",
+                dir,
+                checks.len(),
+                manifest.source,
+                checks.len() - bad.len()
+            );
+            for c in bad {
+                println!("  {:<8} {}", c.status.name(), c.path);
+            }
+        }
+    }
+    Ok(checks.iter().all(|c| c.status == Status::Ok))
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    if args.len() < 2 {
+        eprintln!("usage: {} [--json|--csv] extract_dir...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, dir) in args[1..].iter().enumerate() {
+        match verify(dir, format, i == 0) {
+            Ok(ok) => failed |= !ok,
+            Err(e) => {
+                eprintln!("{}: {}", dir, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
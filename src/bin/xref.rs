@@ -0,0 +1,149 @@
+extern crate fwcutter;
+use fwcutter::baseaddr::{self, Endian};
+use fwcutter::error::{Error, Result};
+use fwcutter::input::Input;
+use fwcutter::output::{self, Format};
+use fwcutter::xref::Xrefs;
+
+use std::env;
+
+struct Options {
+    base: Option<u32>,
+    endian: Option<Endian>,
+    to: Option<u32>,
+}
+
+fn xref(filename: &str, options: &Options, format: Format, first: bool) -> Result<()> {
+    let mmap = Input::open(filename)?;
+    let (base, endian) = match (options.base, options.endian) {
+        (Some(b), Some(e)) => (b, e),
+        // the byte order that makes the most pointers at that base
+        (Some(b), None) => (b, baseaddr::at(&mmap, b)[0].endian),
+        (None, endian) => {
+            // ask baseaddr for whatever we weren't told
+            let best = baseaddr::guess(&mmap, usize::MAX)
+                .into_iter()
+                .find(|c| endian.is_none_or(|e| e == c.endian));
+            match best {
+                Some(c) => (c.base, c.endian),
+                None => (
+                    0,
+                    endian.unwrap_or_else(|| baseaddr::at(&mmap, 0)[0].endian),
+                ),
+            }
+        }
+    };
+    let xrefs = Xrefs::new(&mmap, base, endian);
+
+    if let Some(to) = options.to {
+        // an address, or an offset when it's below the base
+        let offset = match xrefs.offset(to, mmap.len()) {
+            Some(o) => o,
+            None if (to as usize) < mmap.len() => to as usize,
+            None => {
+                return Err(Error::InvalidOffset {
+                    offset: to as usize,
+                    len: mmap.len(),
+                })
+            }
+        };
+        let refs: Vec<_> = xrefs.to(offset).into_iter().cloned().collect();
+        return match format {
+            Format::Json => output::print_json("xref", filename, &refs),
+            Format::Csv => output::print_csv(filename, &refs, first),
+            Format::Text => {
+                println!(
+                    "{}: {} references to {:#010x} (offset {:#x})",
+                    filename,
+                    refs.len(),
+                    base.wrapping_add(offset as u32),
+                    offset
+                );
+                for p in refs.iter() {
+                    println!(
+                        "  {:#010x} (offset {:#x})",
+                        base.wrapping_add(p.offset as u32),
+                        p.offset
+                    );
+                }
+                Ok(())
+            }
+        };
+    }
+
+    match format {
+        Format::Json => output::print_json("xref", filename, &xrefs),
+        Format::Csv => output::print_csv(filename, &xrefs.pointers, first),
+        Format::Text => {
+            println!(
+                "{}: base {:#010x} {}, {} pointers, {} tables",
+                filename,
+                base,
+                endian.name(),
+                xrefs.pointers.len(),
+                xrefs.tables.len()
+            );
+            for t in xrefs.tables.iter() {
+                println!(
+                    "  {:#010x} {:<8} {} entries",
+                    base.wrapping_add(t.offset as u32),
+                    t.kind.name(),
+                    t.len
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+fn usage(name: &str) -> ! {
+    eprintln!(
+        "usage: {} [--json|--csv] [--base addr] [--endian little|big] [--to addr] image.bin...",
+        name
+    );
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let mut options = Options {
+        base: None,
+        endian: None,
+        to: None,
+    };
+    for flag in ["--base", "--endian", "--to"] {
+        if let Some(i) = args.iter().position(|a| a == flag) {
+            let value = match args.get(i + 1) {
+                Some(v) => v.clone(),
+                None => usage(&args[0]),
+            };
+            let ok = match flag {
+                "--base" => output::number(&value).map(|n| options.base = Some(n)),
+                "--to" => output::number(&value).map(|n| options.to = Some(n)),
+                _ => Endian::ALL
+                    .iter()
+                    .find(|e| e.name() == value)
+                    .map(|e| options.endian = Some(*e)),
+            };
+            if ok.is_none() {
+                usage(&args[0]);
+            }
+            args.drain(i..(i + 2));
+        }
+    }
+    if args.len() < 2 {
+        usage(&args[0]);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = xref(filename, &options, format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,632 @@
+use crate::baseaddr::Endian;
+use crate::cut;
+use crate::error::{Error, Result};
+use crate::output::{self, Row};
+use serde::{Serialize, Serializer};
+
+/// 8-bit checks match a random byte one time in 256, only look for them
+/// when we're told where the stored value is
+pub const MIN_SEARCH_WIDTH: u32 = 16;
+
+/// A CRC in the usual "Rocksoft" parametrization, where input and output
+/// are either both reflected or both not.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Crc {
+    pub name: &'static str,
+    pub width: u32,
+    pub poly: u32,
+    pub init: u32,
+    pub reflect: bool,
+    pub xorout: u32,
+}
+
+pub static CRCS: &[Crc] = &[
+    crc("crc16-ccitt-false", 16, 0x1021, 0xffff, false, 0),
+    crc("crc16-xmodem", 16, 0x1021, 0, false, 0),
+    crc("crc16-kermit", 16, 0x1021, 0, true, 0),
+    crc("crc16-arc", 16, 0x8005, 0, true, 0),
+    crc("crc16-modbus", 16, 0x8005, 0xffff, true, 0),
+    crc("crc32", 32, 0x04c1_1db7, 0xffff_ffff, true, 0xffff_ffff),
+    crc(
+        "crc32-bzip2",
+        32,
+        0x04c1_1db7,
+        0xffff_ffff,
+        false,
+        0xffff_ffff,
+    ),
+    crc("crc32-mpeg2", 32, 0x04c1_1db7, 0xffff_ffff, false, 0),
+    crc("crc32-jamcrc", 32, 0x04c1_1db7, 0xffff_ffff, true, 0),
+    crc("crc32c", 32, 0x1edc_6f41, 0xffff_ffff, true, 0xffff_ffff),
+];
+
+const fn crc(
+    name: &'static str,
+    width: u32,
+    poly: u32,
+    init: u32,
+    reflect: bool,
+    xorout: u32,
+) -> Crc {
+    Crc {
+        name,
+        width,
+        poly,
+        init,
+        reflect,
+        xorout,
+    }
+}
+
+fn mask(width: u32) -> u32 {
+    ((1u64 << width) - 1) as u32
+}
+
+fn reflect(v: u32, width: u32) -> u32 {
+    v.reverse_bits() >> (32 - width)
+}
+
+impl Crc {
+    fn table(&self) -> [u32; 256] {
+        let mut table = [0; 256];
+        let top = 1 << (self.width - 1);
+        let poly = reflect(self.poly, self.width);
+        for (i, t) in table.iter_mut().enumerate() {
+            let mut c = i as u32;
+            if self.reflect {
+                for _ in 0..8 {
+                    c = if c & 1 != 0 { (c >> 1) ^ poly } else { c >> 1 };
+                }
+            } else {
+                c <<= self.width - 8;
+                for _ in 0..8 {
+                    c = if c & top != 0 {
+                        (c << 1) ^ self.poly
+                    } else {
+                        c << 1
+                    };
+                }
+            }
+            *t = c & mask(self.width);
+        }
+        table
+    }
+
+    pub fn compute(&self, data: &[u8]) -> u32 {
+        let table = self.table();
+        let mask = mask(self.width);
+        let mut reg = self.init;
+        if self.reflect {
+            reg = reflect(reg, self.width);
+            for b in data {
+                reg = table[((reg ^ *b as u32) & 0xff) as usize] ^ (reg >> 8);
+            }
+        } else {
+            let shift = self.width - 8;
+            for b in data {
+                reg = table[(((reg >> shift) ^ *b as u32) & 0xff) as usize] ^ ((reg << 8) & mask);
+            }
+        }
+        (reg ^ self.xorout) & mask
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Crc(&'static Crc),
+    /// sum of the bytes, truncated to that many bits
+    Sum(u32),
+    /// two's complement of the byte sum, so that the range and the stored
+    /// value add up to 0 (what Intel HEX records do)
+    NegSum(u32),
+    /// sum of the 32-bit words, in the stored value's endianness
+    WordSum,
+    Xor8,
+    /// xor of the 32-bit words, in the stored value's endianness
+    WordXor,
+}
+
+impl Algorithm {
+    pub fn all() -> Vec<Algorithm> {
+        let mut all: Vec<Algorithm> = CRCS.iter().map(Algorithm::Crc).collect();
+        for w in [8, 16, 32] {
+            all.push(Algorithm::Sum(w));
+            all.push(Algorithm::NegSum(w));
+        }
+        all.extend_from_slice(&[Algorithm::WordSum, Algorithm::Xor8, Algorithm::WordXor]);
+        all
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Algorithm::Crc(c) => c.name.to_string(),
+            Algorithm::Sum(w) => format!("sum{}", w),
+            Algorithm::NegSum(w) => format!("-sum{}", w),
+            Algorithm::WordSum => "wordsum32".to_string(),
+            Algorithm::Xor8 => "xor8".to_string(),
+            Algorithm::WordXor => "wordxor32".to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        Algorithm::all().into_iter().find(|a| a.name() == name)
+    }
+
+    /// bits of the stored value
+    pub fn width(&self) -> u32 {
+        match self {
+            Algorithm::Crc(c) => c.width,
+            Algorithm::Sum(w) | Algorithm::NegSum(w) => *w,
+            Algorithm::WordSum | Algorithm::WordXor => 32,
+            Algorithm::Xor8 => 8,
+        }
+    }
+
+    /// whether `endian` changes the result, not only how it's stored
+    fn reads_words(&self) -> bool {
+        matches!(self, Algorithm::WordSum | Algorithm::WordXor)
+    }
+
+    /// the check of `data`, a trailing partial word counts as zero padded
+    pub fn compute(&self, data: &[u8], endian: Endian) -> u32 {
+        let words = || {
+            data.chunks(4).map(move |w| {
+                let mut b = [0; 4];
+                b[..w.len()].copy_from_slice(w);
+                endian.read(&b)
+            })
+        };
+        let sum = || data.iter().fold(0u32, |s, b| s.wrapping_add(*b as u32));
+        match self {
+            Algorithm::Crc(c) => c.compute(data),
+            Algorithm::Sum(w) => sum() & mask(*w),
+            Algorithm::NegSum(w) => sum().wrapping_neg() & mask(*w),
+            Algorithm::WordSum => words().fold(0, u32::wrapping_add),
+            Algorithm::Xor8 => data.iter().fold(0, |x, b| x ^ *b as u32),
+            Algorithm::WordXor => words().fold(0, |x, w| x ^ w),
+        }
+    }
+}
+
+impl Serialize for Algorithm {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.serialize_str(&self.name())
+    }
+}
+
+/// the `width` bits value stored at `offset`
+pub fn stored(data: &[u8], offset: usize, width: u32, endian: Endian) -> Option<u32> {
+    let n = (width / 8) as usize;
+    let b = data.get(offset..(offset + n))?;
+    let mut w = [0; 4];
+    match endian {
+        Endian::Little => w[..n].copy_from_slice(b),
+        Endian::Big => w[(4 - n)..].copy_from_slice(b),
+    }
+    Some(endian.read(&w) & mask(width))
+}
+
+/// An algorithm whose check of `start..end` gives `value`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Match {
+    pub algorithm: Algorithm,
+    pub start: usize,
+    pub end: usize,
+    /// where the value is stored, unknown when it was given to us
+    pub stored_at: Option<usize>,
+    pub endian: Endian,
+    pub value: u32,
+}
+
+impl Row for Match {
+    const HEADER: &'static [&'static str] =
+        &["algorithm", "start", "end", "stored_at", "endian", "value"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.algorithm.name(),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.stored_at.map(|o| o.to_string()).unwrap_or_default(),
+            self.endian.name().to_string(),
+            format!("{:#x}", self.value),
+        ]
+    }
+}
+
+/// The algorithms whose check of `data[start..end]` is `value`.
+pub fn identify(data: &[u8], start: usize, end: usize, value: u32) -> Result<Vec<Match>> {
+    if end > data.len() {
+        return Err(Error::InvalidOffset {
+            offset: end,
+            len: data.len(),
+        });
+    }
+    if start > end {
+        return Err(Error::InvalidOffset {
+            offset: start,
+            len: end,
+        });
+    }
+    let mut matches = Vec::new();
+    for a in Algorithm::all() {
+        let endians = if a.reads_words() {
+            Endian::ALL
+        } else {
+            &[Endian::Little]
+        };
+        for endian in endians {
+            if a.compute(&data[start..end], *endian) == value {
+                matches.push(Match {
+                    algorithm: a,
+                    start,
+                    end,
+                    stored_at: None,
+                    endian: *endian,
+                    value,
+                });
+            }
+        }
+    }
+    Ok(matches)
+}
+
+/// end of `data` once the trailing padding is dropped
+fn trimmed(data: &[u8]) -> usize {
+    let pad = match data.last() {
+        Some(p @ (0 | 0xff)) => *p,
+        _ => return data.len(),
+    };
+    data.iter().rposition(|b| *b != pad).map_or(0, |i| i + 1)
+}
+
+/// Looks for a check stored in the leading or trailing bytes of `data`
+/// (or right before the trailing padding) covering the rest of it.
+pub fn search(data: &[u8]) -> Vec<Match> {
+    let end = trimmed(data);
+    let mut matches = Vec::new();
+    for a in Algorithm::all() {
+        let width = a.width();
+        let n = (width / 8) as usize;
+        if width < MIN_SEARCH_WIDTH || end < 2 * n {
+            continue;
+        }
+        // where the value is stored and the range it covers
+        let mut places = vec![
+            (0, n, data.len()),
+            (0, n, end),
+            (data.len() - n, 0, data.len() - n),
+        ];
+        if end < data.len() {
+            places.push((end - n, 0, end - n));
+        }
+        places.dedup();
+        for (at, start, stop) in places {
+            for endian in Endian::ALL {
+                let value = match stored(data, at, width, *endian) {
+                    Some(v) if v != 0 && v != mask(width) => v,
+                    _ => continue,
+                };
+                if a.compute(&data[start..stop], *endian) == value {
+                    matches.push(Match {
+                        algorithm: a,
+                        start,
+                        end: stop,
+                        stored_at: Some(at),
+                        endian: *endian,
+                        value,
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+/// An offset in a description, negative ones count from the end.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Position(pub i64);
+
+impl Position {
+    fn parse(s: &str) -> Option<Position> {
+        let (neg, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let n = output::number(s)? as i64;
+        Some(Position(if neg { -n } else { n }))
+    }
+
+    fn resolve(&self, len: usize) -> Result<usize> {
+        let o = if self.0 < 0 {
+            len as i64 + self.0
+        } else {
+            self.0
+        };
+        if o < 0 || o as usize > len {
+            return Err(Error::InvalidOffset {
+                offset: o.max(0) as usize,
+                len,
+            });
+        }
+        Ok(o as usize)
+    }
+}
+
+/// Where a check lives and how to compute it, written as
+/// `algorithm=crc32 range=0:-4 at=-4 endian=little [member=path]`, the
+/// range and location being relative to `member` when there's one.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Fixup {
+    pub algorithm: Algorithm,
+    pub start: Position,
+    pub end: Position,
+    pub at: Position,
+    pub endian: Endian,
+    pub member: Option<String>,
+}
+
+impl Fixup {
+    /// parses one description, `offset` being where it starts in its file
+    pub fn parse(s: &str, offset: usize) -> Result<Fixup> {
+        let malformed = |reason| Error::Malformed { offset, reason };
+        let (mut algorithm, mut range, mut at) = (None, None, None);
+        let mut endian = Endian::Little;
+        let mut member = None;
+        for item in s.split_whitespace() {
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| malformed("expected key=value"))?;
+            match key {
+                "algorithm" => {
+                    algorithm = Some(
+                        Algorithm::from_name(value)
+                            .ok_or_else(|| malformed("unknown algorithm"))?,
+                    )
+                }
+                "range" => {
+                    range = value
+                        .split_once(':')
+                        .and_then(|(a, b)| Some((Position::parse(a)?, Position::parse(b)?)));
+                    if range.is_none() {
+                        return Err(malformed("range isn't start:end"));
+                    }
+                }
+                "at" => at = Some(Position::parse(value).ok_or_else(|| malformed("bad location"))?),
+                "endian" => {
+                    endian = match value {
+                        "little" => Endian::Little,
+                        "big" => Endian::Big,
+                        _ => return Err(malformed("endian is little or big")),
+                    }
+                }
+                "member" => member = Some(value.to_string()),
+                _ => return Err(malformed("unknown key")),
+            }
+        }
+        let (start, end) = range.ok_or_else(|| malformed("missing range"))?;
+        Ok(Fixup {
+            algorithm: algorithm.ok_or_else(|| malformed("missing algorithm"))?,
+            start,
+            end,
+            at: at.ok_or_else(|| malformed("missing location"))?,
+            endian,
+            member,
+        })
+    }
+
+    /// one description per line, `#` starts a comment
+    pub fn parse_all(text: &str) -> Result<Vec<Fixup>> {
+        let mut fixups = Vec::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let content = line.split('#').next().unwrap_or("");
+            if !content.trim().is_empty() {
+                fixups.push(Fixup::parse(content, offset)?);
+            }
+            offset += line.len();
+        }
+        Ok(fixups)
+    }
+
+    /// the bytes of `data` the description applies to, the whole of it or
+    /// one member of the archive
+    fn span(&self, data: &[u8]) -> Result<(usize, usize)> {
+        match &self.member {
+            None => Ok((0, data.len())),
+            Some(path) => cut::split(data)?
+                .iter()
+                .find(|m| &m.path == path)
+                .map(|m| (m.offset, m.offset + m.len))
+                .ok_or(Error::Malformed {
+                    offset: 0,
+                    reason: "no such member",
+                }),
+        }
+    }
+
+    /// what's stored and what should be, as offsets of `data`
+    pub fn check(&self, data: &[u8]) -> Result<Check> {
+        let (first, last) = self.span(data)?;
+        let len = last - first;
+        let start = first + self.start.resolve(len)?;
+        let end = first + self.end.resolve(len)?;
+        let at = first + self.at.resolve(len)?;
+        let width = self.algorithm.width();
+        let n = (width / 8) as usize;
+        if start > end {
+            return Err(Error::Malformed {
+                offset: start,
+                reason: "range ends before it starts",
+            });
+        }
+        if at < end && start < at + n {
+            return Err(Error::Malformed {
+                offset: at,
+                reason: "stored value inside the range",
+            });
+        }
+        let stored =
+            stored(&data[..last], at, width, self.endian).ok_or_else(|| Error::Truncated {
+                offset: at,
+                needed: at + n - last,
+            })?;
+        Ok(Check {
+            algorithm: self.algorithm,
+            start,
+            end,
+            at,
+            stored,
+            computed: self.algorithm.compute(&data[start..end], self.endian),
+        })
+    }
+
+    /// rewrites the stored value, and says what it was
+    pub fn apply(&self, data: &mut [u8]) -> Result<Check> {
+        let check = self.check(data)?;
+        let n = (self.algorithm.width() / 8) as usize;
+        let bytes = match self.endian {
+            Endian::Little => check.computed.to_le_bytes()[..n].to_vec(),
+            Endian::Big => check.computed.to_be_bytes()[(4 - n)..].to_vec(),
+        };
+        data[check.at..(check.at + n)].copy_from_slice(&bytes);
+        Ok(check)
+    }
+}
+
+/// A description checked against an image.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Check {
+    pub algorithm: Algorithm,
+    pub start: usize,
+    pub end: usize,
+    pub at: usize,
+    pub stored: u32,
+    pub computed: u32,
+}
+
+impl Check {
+    pub fn ok(&self) -> bool {
+        self.stored == self.computed
+    }
+}
+
+impl Row for Check {
+    const HEADER: &'static [&'static str] = &[
+        "algorithm",
+        "start",
+        "end",
+        "at",
+        "stored",
+        "computed",
+        "ok",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.algorithm.name(),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.at.to_string(),
+            format!("{:#x}", self.stored),
+            format!("{:#x}", self.computed),
+            self.ok().to_string(),
+        ]
+    }
+}
+
+/// The checks found in one member of an archive.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MemberChecks {
+    pub path: String,
+    pub offset: usize,
+    pub matches: Vec<Match>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_values() {
+        let expected = [
+            0x29b1,
+            0x31c3,
+            0x2189,
+            0xbb3d,
+            0x4b37,
+            0xcbf4_3926,
+            0xfc89_1918,
+            0x0376_e6e7,
+            0x340b_c6d9,
+            0xe306_9283,
+        ];
+        for (c, e) in CRCS.iter().zip(expected.iter()) {
+            assert_eq!(c.compute(b"123456789"), *e, "{}", c.name);
+        }
+    }
+
+    #[test]
+    fn sums() {
+        let data = [0x01, 0x02, 0x03, 0xff];
+        assert_eq!(Algorithm::Sum(8).compute(&data, Endian::Little), 0x05);
+        assert_eq!(Algorithm::Sum(16).compute(&data, Endian::Little), 0x105);
+        assert_eq!(Algorithm::NegSum(8).compute(&data, Endian::Little), 0xfb);
+        assert_eq!(Algorithm::WordSum.compute(&data, Endian::Big), 0x0102_03ff);
+        assert_eq!(Algorithm::from_name("-sum8"), Some(Algorithm::NegSum(8)));
+        let m = identify(&data, 0, 4, 0xfb).unwrap();
+        assert!(m.iter().any(|m| m.algorithm == Algorithm::NegSum(8)));
+    }
+
+    #[test]
+    fn identify_range() {
+        let data = [0x01, 0x02, 0x03, 0xff];
+        assert!(identify(&data, 0, 0, 0).is_ok());
+        assert!(matches!(
+            identify(&data, 3, 2, 0),
+            Err(Error::InvalidOffset { offset: 3, len: 2 })
+        ));
+        assert!(matches!(
+            identify(&data, 0, 5, 0),
+            Err(Error::InvalidOffset { offset: 5, len: 4 })
+        ));
+    }
+
+    #[test]
+    fn trailing_crc() {
+        let mut data: Vec<u8> = (0..200u32).map(|i| (i * 7) as u8).collect();
+        let crc = CRCS[5].compute(&data);
+        data.extend_from_slice(&crc.to_be_bytes());
+        data.extend_from_slice(&[0xff; 64]);
+        let m = search(&data);
+        assert_eq!(
+            m,
+            [Match {
+                algorithm: Algorithm::Crc(&CRCS[5]),
+                start: 0,
+                end: 200,
+                stored_at: Some(200),
+                endian: Endian::Big,
+                value: crc,
+            }]
+        );
+    }
+
+    #[test]
+    fn fixup() {
+        let f = Fixup::parse_all("# header\nalgorithm=crc16-xmodem range=0:-2 at=-2 endian=big\n")
+            .unwrap();
+        assert_eq!(f.len(), 1);
+        let mut data = b"123456789\0\0".to_vec();
+        let c = f[0].check(&data).unwrap();
+        assert_eq!((c.start, c.end, c.at, c.stored), (0, 9, 9, 0));
+        assert!(!c.ok());
+        f[0].apply(&mut data).unwrap();
+        assert_eq!(&data[9..], [0x31, 0xc3]);
+        assert!(f[0].check(&data).unwrap().ok());
+
+        assert!(Fixup::parse("algorithm=crc64 range=0:-2 at=-2", 0).is_err());
+        let f = Fixup::parse("algorithm=sum8 range=0:-1 at=-2", 0).unwrap();
+        assert!(f.check(&data).is_err());
+    }
+}
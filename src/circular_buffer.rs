@@ -1,12 +1,13 @@
 use std::fmt;
 use std::ops;
 
+#[derive(Clone)]
 pub struct CircularBuffer {
     buffer: Vec<u8>,
     p: usize,
 }
 
-impl<'a> CircularBuffer {
+impl CircularBuffer {
     pub fn new(size: usize) -> Self {
         CircularBuffer {
             buffer: vec![0; size * 2],
@@ -38,6 +39,16 @@ impl<'a> CircularBuffer {
     }
 }
 
+/// same contents, wherever they start in `buffer`
+impl PartialEq for CircularBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        let l = self.len();
+        l == other.len() && (l == 0 || self[0..l] == other[0..l])
+    }
+}
+
+impl Eq for CircularBuffer {}
+
 impl ops::Index<usize> for CircularBuffer {
     type Output = u8;
 
@@ -151,6 +162,21 @@ mod tests {
         assert_eq!(b[1], b'e');
     }
 
+    #[test]
+    fn equal_contents() {
+        let mut a = CircularBuffer::new(2);
+        let mut b = CircularBuffer::new(2);
+        for c in b"abc" {
+            a.push(*c);
+        }
+        b.push(b'b');
+        b.push(b'c');
+        assert_eq!(a, b);
+        b.push(b'd');
+        assert_ne!(a, b);
+        assert_eq!(CircularBuffer::new(0), CircularBuffer::new(0));
+    }
+
     #[test]
     fn sliceone() {
         let mut b = CircularBuffer::new(2);
@@ -0,0 +1,141 @@
+use crate::error::{Error, Result};
+use blowfish::cipher::generic_array::GenericArray;
+use blowfish::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use blowfish::Blowfish;
+
+/// `.cube` print jobs are BFB G-code encrypted with Blowfish in ECB mode,
+/// with this key for every printer.
+pub static KEY: &[u8] = b"221BBakerMycroft";
+pub const BLOCK_SIZE: usize = 8;
+
+fn cipher() -> Blowfish {
+    Blowfish::new_from_slice(KEY).expect("the key has a valid length")
+}
+
+/// Pads `gcode` with PKCS#5 and encrypts it into something the printer
+/// accepts as a `.cube` file.
+pub fn encrypt(gcode: &[u8]) -> Vec<u8> {
+    let pad = BLOCK_SIZE - gcode.len() % BLOCK_SIZE;
+    let mut out = gcode.to_vec();
+    out.resize(gcode.len() + pad, pad as u8);
+
+    let cipher = cipher();
+    for block in out.chunks_mut(BLOCK_SIZE) {
+        cipher.encrypt_block(GenericArray::from_mut_slice(block));
+    }
+    out
+}
+
+/// Decrypts a `.cube` file to G-code and strips its PKCS#5 padding.
+pub fn decrypt(data: &[u8]) -> Result<Vec<u8>> {
+    if data.is_empty() {
+        return Err(Error::Truncated {
+            offset: 0,
+            needed: BLOCK_SIZE,
+        });
+    }
+    if !data.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(Error::Malformed {
+            offset: data.len() - data.len() % BLOCK_SIZE,
+            reason: "not a whole number of blowfish blocks",
+        });
+    }
+
+    let cipher = cipher();
+    let mut out = data.to_vec();
+    for block in out.chunks_mut(BLOCK_SIZE) {
+        cipher.decrypt_block(GenericArray::from_mut_slice(block));
+    }
+
+    let pad = out[out.len() - 1] as usize;
+    if pad == 0 || pad > BLOCK_SIZE || out[(out.len() - pad)..].iter().any(|c| *c as usize != pad) {
+        return Err(Error::Malformed {
+            offset: data.len() - BLOCK_SIZE,
+            reason: "bad padding, is this really a .cube file?",
+        });
+    }
+    out.truncate(out.len() - pad);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypt_blocks(data: &[u8]) -> Vec<u8> {
+        let cipher = cipher();
+        let mut out = data.to_vec();
+        for block in out.chunks_mut(BLOCK_SIZE) {
+            cipher.encrypt_block(GenericArray::from_mut_slice(block));
+        }
+        out
+    }
+
+    #[test]
+    fn blowfish() {
+        // the usual all zero test vector, to make sure we're plain blowfish
+        let c: Blowfish = Blowfish::new_from_slice(&[0; 8]).unwrap();
+        let mut block = GenericArray::clone_from_slice(&[0; 8]);
+        c.encrypt_block(&mut block);
+        assert_eq!(
+            block.as_slice(),
+            [0x4e, 0xf9, 0x97, 0x45, 0x61, 0x98, 0xdd, 0x78]
+        );
+    }
+
+    #[test]
+    fn gcode() {
+        let cube = encrypt_blocks(b"G1 X1.0\nM103\n\x03\x03\x03");
+        assert_eq!(decrypt(&cube).unwrap(), b"G1 X1.0\nM103\n");
+
+        let cube = encrypt_blocks(b"M104 S1\n\x08\x08\x08\x08\x08\x08\x08\x08");
+        assert_eq!(decrypt(&cube).unwrap(), b"M104 S1\n");
+    }
+
+    #[test]
+    fn bad_sizes() {
+        match decrypt(&[]) {
+            Err(Error::Truncated { offset: 0, .. }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        match decrypt(&[0; 12]) {
+            Err(Error::Malformed { offset: 8, .. }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn bad_padding() {
+        let cube = encrypt_blocks(b"G1 X1.0\nM103\n\x03\x02\x03");
+        match decrypt(&cube) {
+            Err(Error::Malformed { offset: 8, .. }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn padding() {
+        assert_eq!(encrypt(b"").len(), 8);
+        assert_eq!(encrypt(b"1234567").len(), 8);
+        assert_eq!(encrypt(b"12345678").len(), 16);
+        assert_eq!(
+            encrypt(b"G1 X1.0\nM103\n"),
+            encrypt_blocks(b"G1 X1.0\nM103\n\x03\x03\x03")
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut gcode = b"^Firmware:V1.14B\n^Minfirmware:V1.14B\nM104 S215\n".to_vec();
+        for i in 0..200 {
+            gcode.extend(format!("G1 X{}.0 Y{}.5 Z0.2 F3000\nM101\n", i, i * 2).bytes());
+        }
+        for len in 0..gcode.len().min(40) {
+            let cube = encrypt(&gcode[..len]);
+            assert_eq!(decrypt(&cube).unwrap(), &gcode[..len]);
+            assert_eq!(encrypt(&decrypt(&cube).unwrap()), cube);
+        }
+        let cube = encrypt(&gcode);
+        assert_eq!(encrypt(&decrypt(&cube).unwrap()), cube);
+    }
+}
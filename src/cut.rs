@@ -0,0 +1,173 @@
+use crate::error::{Error, Result};
+use crate::output::Row;
+use crate::pattern::Pattern;
+use serde::Serialize;
+use std::path::{Component, PathBuf};
+
+/// A file stored in a `firmware.ar`, as a range of the archive.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Member {
+    /// path as stored in the archive, with `\` turned into `/`
+    pub path: String,
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Member {
+    pub fn data<'a>(&self, archive: &'a [u8]) -> &'a [u8] {
+        &archive[self.offset..(self.offset + self.len)]
+    }
+
+    /// the path without any root or `..`, safe to join to an output dir
+    pub fn relative_path(&self) -> PathBuf {
+        PathBuf::from(&self.path)
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(c) => Some(c),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Row for Member {
+    const HEADER: &'static [&'static str] = &["path", "offset", "len"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.path.clone(),
+            self.offset.to_string(),
+            self.len.to_string(),
+        ]
+    }
+}
+
+/// members are separated by a long run of zeroes, preceded by their name
+pub fn separator() -> Pattern<'static> {
+    Pattern::new(b"\0").lookback(40).repeats(15 * 16)
+}
+
+/// the filename that sits right before the separator `p` just matched
+pub fn filename(p: &Pattern) -> Option<String> {
+    let b = &p._ro_get();
+    let l = b.len();
+    for i in 1..(l - 1) {
+        if b[l - i - 1] == 0 {
+            let s: String = b[(l - i)..]
+                .iter()
+                .map(|c| match c {
+                    b'\\' => '/',
+                    _ => *c as char,
+                })
+                .collect();
+            return Some(s);
+        }
+    }
+    None
+}
+
+/// Lists the members of a `firmware.ar`, this is what `cut` extracts.
+pub fn split(data: &[u8]) -> Result<Vec<Member>> {
+    let mut p = separator();
+    let mut members = Vec::new();
+    let mut current: Option<Member> = None;
+    // waiting for the end of a separator to know where the next member starts
+    let mut in_separator = false;
+    let mut last_p = 0;
+    let mut last_n = 0;
+
+    for (i, c) in data.iter().enumerate() {
+        let read = i + 1;
+        match p.push(*c) {
+            Some(n) => {
+                let n = n as usize;
+                if read - n == last_p {
+                    continue;
+                }
+                if let Some(s) = filename(&p) {
+                    if let Some(mut m) = current.take() {
+                        //        the_buffer   the_match  the_file  the_padding
+                        m.len = match (i - m.offset).checked_sub(last_n + s.len() + 1) {
+                            Some(len) if data[m.offset] != b'\0' => len,
+                            _ => {
+                                return Err(Error::InvalidOffset {
+                                    offset: last_p,
+                                    len: data.len(),
+                                })
+                            }
+                        };
+                        members.push(m);
+                    }
+                    current = Some(Member {
+                        path: s,
+                        offset: data.len(),
+                        len: 0,
+                    });
+                    in_separator = true;
+                }
+                last_p = read - n;
+                last_n = n;
+            }
+            None => {
+                if in_separator {
+                    if let Some(ref mut m) = current {
+                        m.offset = i;
+                    }
+                    in_separator = false;
+                }
+            }
+        }
+    }
+
+    if let Some(mut m) = current {
+        m.len = data.len() - m.offset;
+        members.push(m);
+    }
+    Ok(members)
+}
+
+/// A `firmware.ar` holding `members`, for the tests.
+#[cfg(test)]
+pub(crate) fn archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut ar = Vec::new();
+    for (name, data) in members {
+        ar.extend_from_slice(b"x\0");
+        ar.extend_from_slice(name.as_bytes());
+        ar.extend_from_slice(&[0; 300]);
+        ar.extend_from_slice(data);
+    }
+    ar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(split(&[]).unwrap(), []);
+        assert_eq!(split(b"no separator here").unwrap(), []);
+    }
+
+    #[test]
+    fn members() {
+        let ar = archive(&[("dir\\a.hex", b"first file"), ("b.bin", b"second")]);
+        let m = split(&ar).unwrap();
+
+        assert_eq!(m.len(), 2);
+        assert_eq!(m[0].path, "dir/a.hex");
+        assert_eq!(m[0].data(&ar), b"first file");
+        assert_eq!(m[1].path, "b.bin");
+        assert_eq!(m[1].data(&ar), b"second");
+    }
+
+    #[test]
+    fn relative_path() {
+        let m = Member {
+            path: "/../etc/passwd".to_string(),
+            offset: 0,
+            len: 0,
+        };
+        assert_eq!(m.relative_path(), PathBuf::from("etc/passwd"));
+    }
+}
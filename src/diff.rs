@@ -0,0 +1,315 @@
+use crate::cut;
+use crate::error::Result;
+use crate::mpfs;
+use crate::output::{self, Row};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// differences closer than this are reported as one range
+const MERGE_GAP: usize = 8;
+/// how many bytes of each side we keep to show a range
+const CONTEXT: usize = 16;
+
+pub fn sha256(data: &[u8]) -> String {
+    output::to_hex(&Sha256::digest(data))
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FileInfo {
+    pub len: usize,
+    pub sha256: String,
+}
+
+impl FileInfo {
+    pub fn new(data: &[u8]) -> Self {
+        FileInfo {
+            len: data.len(),
+            sha256: sha256(data),
+        }
+    }
+}
+
+/// A run of differing bytes, `old` and `new` only hold the first few of them.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Range {
+    pub offset: usize,
+    pub len: usize,
+    #[serde(serialize_with = "output::hex")]
+    pub old: Vec<u8>,
+    #[serde(serialize_with = "output::hex")]
+    pub new: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum Change {
+    Added {
+        path: String,
+        new: FileInfo,
+    },
+    Removed {
+        path: String,
+        old: FileInfo,
+    },
+    Changed {
+        path: String,
+        old: FileInfo,
+        new: FileInfo,
+        ranges: Vec<Range>,
+        /// file by file comparison when both sides hold an MPFS image
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mpfs: Option<Vec<Change>>,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } => path,
+            Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl Row for Change {
+    const HEADER: &'static [&'static str] = &[
+        "change",
+        "path",
+        "old_len",
+        "old_sha256",
+        "new_len",
+        "new_sha256",
+        "ranges",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        let info = |i: Option<&FileInfo>| match i {
+            Some(i) => vec![i.len.to_string(), i.sha256.clone()],
+            None => vec![String::new(), String::new()],
+        };
+        let (change, old, new, ranges) = match self {
+            Change::Added { new, .. } => ("added", None, Some(new), 0),
+            Change::Removed { old, .. } => ("removed", Some(old), None, 0),
+            Change::Changed {
+                old, new, ranges, ..
+            } => ("changed", Some(old), Some(new), ranges.len()),
+        };
+        let mut row = vec![change.to_string(), self.path().to_string()];
+        row.extend(info(old));
+        row.extend(info(new));
+        row.push(ranges.to_string());
+        row
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Diff {
+    pub old: FileInfo,
+    pub new: FileInfo,
+    pub unchanged: usize,
+    pub changes: Vec<Change>,
+}
+
+fn context(data: &[u8], offset: usize) -> Vec<u8> {
+    data.iter().skip(offset).take(CONTEXT).cloned().collect()
+}
+
+/// The runs of bytes that differ between `old` and `new`, a length change
+/// shows up as a last range covering the tail of the longest one.
+pub fn ranges(old: &[u8], new: &[u8]) -> Vec<Range> {
+    let mut ranges: Vec<Range> = Vec::new();
+    let len = old.len().max(new.len());
+
+    for i in 0..len {
+        if old.get(i) == new.get(i) {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(r) if i <= r.offset + r.len + MERGE_GAP => r.len = i + 1 - r.offset,
+            _ => ranges.push(Range {
+                offset: i,
+                len: 1,
+                old: vec![],
+                new: vec![],
+            }),
+        }
+    }
+
+    for r in ranges.iter_mut() {
+        r.old = context(old, r.offset);
+        r.new = context(new, r.offset);
+    }
+    ranges
+}
+
+type Pair<'a> = (Option<&'a [u8]>, Option<&'a [u8]>);
+
+/// pairs up `old` and `new` by path and reports what changed
+fn compare<'a>(old: Vec<(String, &'a [u8])>, new: Vec<(String, &'a [u8])>) -> (usize, Vec<Change>) {
+    let mut paired: BTreeMap<String, Pair> = BTreeMap::new();
+    for (path, data) in old {
+        paired.entry(path).or_default().0 = Some(data);
+    }
+    for (path, data) in new {
+        paired.entry(path).or_default().1 = Some(data);
+    }
+
+    let mut unchanged = 0;
+    let mut changes = Vec::new();
+    for (path, pair) in paired {
+        match pair {
+            (Some(o), Some(n)) if o == n => unchanged += 1,
+            (Some(o), Some(n)) => changes.push(Change::Changed {
+                path,
+                old: FileInfo::new(o),
+                new: FileInfo::new(n),
+                ranges: ranges(o, n),
+                mpfs: compare_mpfs(o, n),
+            }),
+            (Some(o), None) => changes.push(Change::Removed {
+                path,
+                old: FileInfo::new(o),
+            }),
+            (None, Some(n)) => changes.push(Change::Added {
+                path,
+                new: FileInfo::new(n),
+            }),
+            (None, None) => unreachable!(),
+        }
+    }
+    (unchanged, changes)
+}
+
+/// names aren't unique in archives or MPFS tables, number the duplicates so
+/// they don't hide each other
+fn unique(names: Vec<(String, &[u8])>) -> Vec<(String, &[u8])> {
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    names
+        .into_iter()
+        .map(|(name, data)| {
+            let n = seen.entry(name.clone()).or_insert(0);
+            *n += 1;
+            match *n {
+                1 => (name, data),
+                n => (format!("{}#{}", name, n), data),
+            }
+        })
+        .collect()
+}
+
+fn mpfs_files(data: &[u8]) -> Option<Vec<(String, &[u8])>> {
+    let image = &data[mpfs::find(data)?..];
+    let (_, files) = mpfs::parse(image).ok()?;
+    let files = files
+        .iter()
+        .map(|f| Some((f.name(), f.data(image).ok()?)))
+        .collect::<Option<Vec<_>>>()?;
+    Some(unique(files))
+}
+
+fn compare_mpfs(old: &[u8], new: &[u8]) -> Option<Vec<Change>> {
+    let (_, changes) = compare(mpfs_files(old)?, mpfs_files(new)?);
+    Some(changes)
+}
+
+/// Compares two `firmware.ar` member by member.
+pub fn archives(old: &[u8], new: &[u8]) -> Result<Diff> {
+    let members = |data| -> Result<Vec<(String, &[u8])>> {
+        Ok(unique(
+            cut::split(data)?
+                .into_iter()
+                .map(|m| {
+                    let d = m.data(data);
+                    (m.path, d)
+                })
+                .collect(),
+        ))
+    };
+    let (unchanged, changes) = compare(members(old)?, members(new)?);
+    Ok(Diff {
+        old: FileInfo::new(old),
+        new: FileInfo::new(new),
+        unchanged,
+        changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_ranges() {
+        let old = b"0123456789abcdefghijklmnopqrstuvwxyz";
+        let new = b"0123x567y9abcdefghijKlmnopqrstuvwxyz!!";
+        let r = ranges(old, new);
+        let r: Vec<(usize, usize)> = r.iter().map(|r| (r.offset, r.len)).collect();
+        assert_eq!(r, [(4, 5), (20, 1), (36, 2)]);
+        assert!(ranges(old, old).is_empty());
+    }
+
+    #[test]
+    fn range_context() {
+        let r = ranges(b"abcd", b"abXd");
+        assert_eq!(r[0].old, b"cd");
+        assert_eq!(r[0].new, b"Xd");
+    }
+
+    #[test]
+    fn members() {
+        let old = cut::archive(&[
+            ("same", b"same data"),
+            ("gone", b"old data"),
+            ("changed", b"one two"),
+        ]);
+        let new = cut::archive(&[
+            ("same", b"same data"),
+            ("changed", b"one too"),
+            ("added", b"new data"),
+        ]);
+
+        let diff = archives(&old, &new).unwrap();
+        assert_eq!(diff.unchanged, 1);
+        let paths: Vec<&str> = diff.changes.iter().map(|c| c.path()).collect();
+        assert_eq!(paths, ["added", "changed", "gone"]);
+        match &diff.changes[1] {
+            Change::Changed { ranges, mpfs, .. } => {
+                assert_eq!(ranges.len(), 1);
+                assert_eq!(ranges[0].offset, 5);
+                assert_eq!(*mpfs, None);
+            }
+            c => panic!("unexpected {:?}", c),
+        }
+    }
+
+    #[test]
+    fn duplicate_members() {
+        let old = cut::archive(&[("a.bin", b"first"), ("a.bin", b"second")]);
+        let new = cut::archive(&[("a.bin", b"FIRST"), ("a.bin", b"second")]);
+
+        let diff = archives(&old, &new).unwrap();
+        assert_eq!(diff.unchanged, 1);
+        let paths: Vec<&str> = diff.changes.iter().map(|c| c.path()).collect();
+        assert_eq!(paths, ["a.bin"]);
+    }
+
+    #[test]
+    fn mpfs_members() {
+        let old = mpfs::image(&[("a.js", b"1"), ("b.js", b"22")]);
+        let new = mpfs::image(&[("a.js", b"1"), ("b.js", b"23")]);
+        let old = cut::archive(&[("web.bin", &old)]);
+        let new = cut::archive(&[("web.bin", &new)]);
+
+        let diff = archives(&old, &new).unwrap();
+        match &diff.changes[0] {
+            Change::Changed {
+                mpfs: Some(files), ..
+            } => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].path(), "b.js");
+            }
+            c => panic!("unexpected {:?}", c),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::output::Row;
+use serde::Serialize;
+
+pub const DEFAULT_BLOCK_SIZE: usize = 1024;
+/// above this (normalized) entropy a block looks compressed or encrypted
+pub const HIGH: f64 = 0.95;
+/// below this it's padding, tables or other very regular data
+pub const LOW: f64 = 0.25;
+
+/// Shannon entropy of `data`, normalized to 0..1 (that is bits per byte / 8).
+pub fn shannon(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for c in data {
+        counts[*c as usize] += 1;
+    }
+    let len = data.len() as f64;
+    let bits: f64 = counts
+        .iter()
+        .filter(|n| **n > 0)
+        .map(|n| {
+            let p = *n as f64 / len;
+            p * (1.0 / p).log2()
+        })
+        .sum();
+    bits / 8.0
+}
+
+/// `shannon` scaled by the most a block of `data.len()` bytes can reach,
+/// log2(len) bits under 256 bytes, so short blocks can still be high.
+pub fn relative(data: &[u8]) -> f64 {
+    if data.len() < 2 {
+        return 0.0;
+    }
+    let max = (data.len().min(256) as f64).log2() / 8.0;
+    (shannon(data) / max).min(1.0)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Block {
+    pub offset: usize,
+    pub entropy: f64,
+}
+
+impl Row for Block {
+    const HEADER: &'static [&'static str] = &["offset", "entropy"];
+
+    fn row(&self) -> Vec<String> {
+        vec![self.offset.to_string(), format!("{:.4}", self.entropy)]
+    }
+}
+
+/// the `relative` entropy of every `block_size` bytes of `data`, the last
+/// block may be shorter
+pub fn blocks(data: &[u8], block_size: usize) -> Vec<Block> {
+    let block_size = block_size.max(1);
+    data.chunks(block_size)
+        .enumerate()
+        .map(|(i, c)| Block {
+            offset: i * block_size,
+            entropy: relative(c),
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Low,
+    Medium,
+    High,
+}
+
+impl Level {
+    pub fn of(entropy: f64) -> Self {
+        if entropy >= HIGH {
+            Level::High
+        } else if entropy <= LOW {
+            Level::Low
+        } else {
+            Level::Medium
+        }
+    }
+}
+
+/// Consecutive blocks of the same level.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Region {
+    pub offset: usize,
+    pub len: usize,
+    pub level: Level,
+    pub mean: f64,
+}
+
+/// Where the level changes, `offset` being the first block of the new level.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Transition {
+    pub offset: usize,
+    pub from: Level,
+    pub to: Level,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Map {
+    pub block_size: usize,
+    pub blocks: Vec<Block>,
+    pub regions: Vec<Region>,
+    pub transitions: Vec<Transition>,
+}
+
+impl Map {
+    pub fn new(data: &[u8], block_size: usize) -> Self {
+        let block_size = block_size.max(1);
+        let blocks = blocks(data, block_size);
+        let mut regions: Vec<Region> = Vec::new();
+        let mut transitions = Vec::new();
+
+        for b in blocks.iter() {
+            let len = block_size.min(data.len() - b.offset);
+            let level = Level::of(b.entropy);
+            match regions.last_mut() {
+                Some(r) if r.level == level => {
+                    r.mean += b.entropy * len as f64;
+                    r.len += len;
+                }
+                last => {
+                    if let Some(r) = last {
+                        transitions.push(Transition {
+                            offset: b.offset,
+                            from: r.level,
+                            to: level,
+                        });
+                    }
+                    regions.push(Region {
+                        offset: b.offset,
+                        len,
+                        level,
+                        mean: b.entropy * len as f64,
+                    })
+                }
+            }
+        }
+        for r in regions.iter_mut() {
+            r.mean /= r.len as f64;
+        }
+
+        Map {
+            block_size,
+            blocks,
+            regions,
+            transitions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extremes() {
+        assert_eq!(shannon(&[]), 0.0);
+        assert_eq!(shannon(&[0; 100]), 0.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert!((shannon(&all) - 1.0).abs() < 1e-9);
+        assert!((shannon(b"abababab") - 1.0 / 8.0).abs() < 1e-9);
+        assert!((relative(b"abababab") - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(relative(b"a"), 0.0);
+    }
+
+    #[test]
+    fn short_blocks() {
+        let mut x: u32 = 7;
+        let random: Vec<u8> = (0..64)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345);
+                (x >> 16) as u8
+            })
+            .collect();
+        let map = Map::new(&random, 64);
+        assert_eq!(map.regions.len(), 1);
+        assert_eq!(map.regions[0].level, Level::High);
+        assert!(shannon(&random) < HIGH);
+    }
+
+    #[test]
+    fn regions() {
+        let mut data = vec![0; 512];
+        data.extend((0..512).map(|c: u32| (c * 97 % 256) as u8));
+        data.extend(vec![0xff; 100]);
+
+        let map = Map::new(&data, 256);
+        assert_eq!(map.blocks.len(), 5);
+        let r: Vec<(usize, usize, Level)> = map
+            .regions
+            .iter()
+            .map(|r| (r.offset, r.len, r.level))
+            .collect();
+        assert_eq!(
+            r,
+            [
+                (0, 512, Level::Low),
+                (512, 512, Level::High),
+                (1024, 100, Level::Low)
+            ]
+        );
+        assert_eq!(
+            map.transitions,
+            [
+                Transition {
+                    offset: 512,
+                    from: Level::Low,
+                    to: Level::High
+                },
+                Transition {
+                    offset: 1024,
+                    from: Level::High,
+                    to: Level::Low
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong while parsing a firmware image.
+///
+/// Offsets are byte offsets into the input being parsed, so a failing file
+/// can be looked at with a hexdump straight away.
+#[derive(Debug)]
+pub enum Error {
+    /// input ended before `needed` bytes could be read at `offset`
+    Truncated {
+        offset: usize,
+        needed: usize,
+    },
+    /// the magic found at `offset` isn't the one we expected
+    BadMagic {
+        offset: usize,
+        found: Vec<u8>,
+    },
+    /// an offset that points outside of an input of `len` bytes
+    InvalidOffset {
+        offset: usize,
+        len: usize,
+    },
+    /// checksum stored at `offset` doesn't match what we computed
+    BadChecksum {
+        offset: usize,
+        expected: u32,
+        found: u32,
+    },
+    /// something at `offset` we can't make sense of
+    Malformed {
+        offset: usize,
+        reason: &'static str,
+    },
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Truncated { offset, needed } => write!(
+                f,
+                "truncated input at {:#010x}: needed {} more bytes",
+                offset, needed
+            ),
+            Error::BadMagic { offset, found } => {
+                write!(f, "bad magic at {:#010x}: found", offset)?;
+                for c in found {
+                    write!(f, " {:02x}", c)?;
+                }
+                Ok(())
+            }
+            Error::InvalidOffset { offset, len } => write!(
+                f,
+                "invalid offset {:#010x} (input is {:#x} bytes long)",
+                offset, len
+            ),
+            Error::BadChecksum {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "bad checksum at {:#010x}: expected {:#010x}, found {:#010x}",
+                offset, expected, found
+            ),
+            Error::Malformed { offset, reason } => {
+                write!(f, "malformed input at {:#010x}: {}", offset, reason)
+            }
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,258 @@
+use crate::cut;
+use crate::diff;
+use crate::error::{Error, Result};
+use crate::output::Row;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// the releases we know about, shipped with the tools
+static BUILTIN: &str = include_str!("../fingerprints.json");
+/// overrides where the user's own releases are kept
+pub const ENV_PATH: &str = "FWCUTTER_FINGERPRINTS";
+
+/// A known `firmware.ar` and the hashes of its members.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    pub model: String,
+    pub version: String,
+    #[serde(default)]
+    pub notes: String,
+    pub sha256: String,
+    /// member path to its SHA-256
+    pub members: BTreeMap<String, String>,
+}
+
+impl Release {
+    /// fingerprints the archive in `data`
+    pub fn new(data: &[u8], model: &str, version: &str, notes: &str) -> Result<Self> {
+        Ok(Release {
+            model: model.to_string(),
+            version: version.to_string(),
+            notes: notes.to_string(),
+            sha256: diff::sha256(data),
+            members: members(data)?,
+        })
+    }
+}
+
+fn members(data: &[u8]) -> Result<BTreeMap<String, String>> {
+    Ok(cut::split(data)?
+        .iter()
+        .map(|m| (m.path.clone(), diff::sha256(m.data(data))))
+        .collect())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Changed,
+    /// only in the archive we're looking at
+    Added,
+    /// only in the known release
+    Removed,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Changed => "changed",
+            Status::Added => "added",
+            Status::Removed => "removed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Difference {
+    pub path: String,
+    pub status: Status,
+}
+
+impl Row for Difference {
+    const HEADER: &'static [&'static str] = &["path", "status"];
+
+    fn row(&self) -> Vec<String> {
+        vec![self.path.clone(), self.status.name().to_string()]
+    }
+}
+
+/// The known release sharing the most members with an archive.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Closest {
+    pub release: Release,
+    /// members with the same path and content
+    pub same: usize,
+    pub differences: Vec<Difference>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Identification {
+    pub sha256: String,
+    /// the archive is exactly this release
+    pub known: bool,
+    pub closest: Option<Closest>,
+}
+
+fn compare(ours: &BTreeMap<String, String>, release: &Release) -> Closest {
+    let mut same = 0;
+    let mut differences = Vec::new();
+    for (path, sha) in ours.iter() {
+        match release.members.get(path) {
+            Some(s) if s == sha => same += 1,
+            Some(_) => differences.push((path, Status::Changed)),
+            None => differences.push((path, Status::Added)),
+        }
+    }
+    for path in release.members.keys().filter(|p| !ours.contains_key(*p)) {
+        differences.push((path, Status::Removed));
+    }
+    differences.sort();
+    Closest {
+        release: release.clone(),
+        same,
+        differences: differences
+            .into_iter()
+            .map(|(path, status)| Difference {
+                path: path.clone(),
+                status,
+            })
+            .collect(),
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Database {
+    pub releases: Vec<Release>,
+}
+
+impl Database {
+    /// a JSON array of releases
+    pub fn parse(text: &str) -> Result<Self> {
+        let releases = serde_json::from_str(text).map_err(|e| Error::Malformed {
+            offset: e.column(),
+            reason: "not a fingerprint database",
+        })?;
+        Ok(Database { releases })
+    }
+
+    /// where `record` writes, `$FWCUTTER_FINGERPRINTS` or in `~/.config`
+    pub fn user_path() -> Option<PathBuf> {
+        if let Some(p) = env::var_os(ENV_PATH) {
+            return Some(PathBuf::from(p));
+        }
+        let home = env::var_os("HOME")?;
+        Some(PathBuf::from(home).join(".config/fwcutter/fingerprints.json"))
+    }
+
+    fn user() -> Result<Self> {
+        match Database::user_path().map(fs::read_to_string) {
+            Some(Ok(text)) => Database::parse(&text),
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(Database::default()),
+        }
+    }
+
+    /// the releases shipped with the tools and the user's own
+    pub fn load() -> Result<Self> {
+        let mut db = Database::parse(BUILTIN)?;
+        db.releases.extend(Database::user()?.releases);
+        Ok(db)
+    }
+
+    /// adds `release` to the user's database
+    pub fn record(release: Release) -> Result<PathBuf> {
+        let path = Database::user_path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no HOME for the fingerprints")
+        })?;
+        let mut db = Database::user()?;
+        db.releases.push(release);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let f = fs::File::create(&path)?;
+        serde_json::to_writer_pretty(f, &db.releases).map_err(io::Error::from)?;
+        Ok(path)
+    }
+
+    /// Whether the archive in `data` is a known release, and if not which
+    /// one it's closest to.
+    pub fn identify(&self, data: &[u8]) -> Result<Identification> {
+        let sha256 = diff::sha256(data);
+        let ours = members(data)?;
+        let closest = self
+            .releases
+            .iter()
+            .map(|r| compare(&ours, r))
+            .filter(|c| c.same > 0 || c.release.sha256 == sha256)
+            .max_by_key(|c| {
+                (
+                    c.release.sha256 == sha256,
+                    c.same,
+                    usize::MAX - c.differences.len(),
+                )
+            });
+        Ok(Identification {
+            known: closest.as_ref().is_some_and(|c| c.release.sha256 == sha256),
+            sha256,
+            closest,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin() {
+        Database::parse(BUILTIN).unwrap();
+    }
+
+    #[test]
+    fn identify() {
+        let v1 = cut::archive(&[
+            ("boot.bin", b"boot"),
+            ("app.hex", b"app v1"),
+            ("web.bin", b"web"),
+        ]);
+        let v2 = cut::archive(&[
+            ("boot.bin", b"boot"),
+            ("app.hex", b"app v2"),
+            ("new.bin", b"new"),
+        ]);
+        let db = Database {
+            releases: vec![
+                Release::new(&cut::archive(&[("other", b"other")]), "x", "0", "").unwrap(),
+                Release::new(&v1, "CubePro", "1.0", "first").unwrap(),
+            ],
+        };
+
+        let id = db.identify(&v1).unwrap();
+        assert!(id.known);
+        assert_eq!(id.closest.unwrap().release.version, "1.0");
+
+        let id = db.identify(&v2).unwrap();
+        assert!(!id.known);
+        let c = id.closest.unwrap();
+        assert_eq!((c.release.version.as_str(), c.same), ("1.0", 1));
+        let d: Vec<(&str, Status)> = c
+            .differences
+            .iter()
+            .map(|d| (d.path.as_str(), d.status))
+            .collect();
+        assert_eq!(
+            d,
+            [
+                ("app.hex", Status::Changed),
+                ("new.bin", Status::Added),
+                ("web.bin", Status::Removed)
+            ]
+        );
+
+        assert_eq!(db.identify(b"not an archive").unwrap().closest, None);
+    }
+}
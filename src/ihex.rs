@@ -0,0 +1,262 @@
+use crate::error::{Error, Result};
+use crate::output::Row;
+use serde::{Serialize, Serializer};
+
+const DATA: u8 = 0x00;
+const EOF: u8 = 0x01;
+const EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const START_SEGMENT_ADDRESS: u8 = 0x03;
+const EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const START_LINEAR_ADDRESS: u8 = 0x05;
+
+/// A contiguous run of data records.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Segment {
+    pub address: u32,
+    /// where the first record of the segment is in the hex file
+    pub offset: usize,
+    #[serde(rename = "len", serialize_with = "len")]
+    pub data: Vec<u8>,
+}
+
+fn len<S: Serializer>(a: &[u8], s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_u64(a.len() as u64)
+}
+
+impl Segment {
+    pub fn end(&self) -> u32 {
+        self.address.wrapping_add(self.data.len() as u32)
+    }
+}
+
+impl Row for Segment {
+    const HEADER: &'static [&'static str] = &["address", "offset", "len"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            format!("{:#010x}", self.address),
+            self.offset.to_string(),
+            self.data.len().to_string(),
+        ]
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Image {
+    pub segments: Vec<Segment>,
+    /// entry point from a start address record, if any
+    pub start: Option<u32>,
+}
+
+fn nibble(c: u8, offset: usize) -> Result<u8> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Error::Malformed {
+            offset,
+            reason: "not a hex digit",
+        }),
+    }
+}
+
+/// decodes one `:llaaaatt...cc` line starting at `offset`
+fn record(line: &[u8], offset: usize) -> Result<(u8, u16, Vec<u8>)> {
+    if line.first() != Some(&b':') {
+        return Err(Error::BadMagic {
+            offset,
+            found: line.iter().take(1).cloned().collect(),
+        });
+    }
+    let digits = &line[1..];
+    if !digits.len().is_multiple_of(2) {
+        return Err(Error::Malformed {
+            offset,
+            reason: "odd number of hex digits",
+        });
+    }
+    let mut bytes = Vec::with_capacity(digits.len() / 2);
+    for (i, d) in digits.chunks(2).enumerate() {
+        let o = offset + 1 + i * 2;
+        bytes.push(nibble(d[0], o)? << 4 | nibble(d[1], o + 1)?);
+    }
+    let needed = 5 + bytes.first().map_or(0, |l| *l as usize);
+    if bytes.len() < needed {
+        return Err(Error::Truncated {
+            offset,
+            needed: needed - bytes.len(),
+        });
+    }
+    if bytes.len() > needed {
+        return Err(Error::Malformed {
+            offset,
+            reason: "record is longer than its length byte says",
+        });
+    }
+
+    let (body, cc) = bytes.split_at(4 + bytes[0] as usize);
+    let sum = body.iter().fold(0u8, |s, c| s.wrapping_add(*c));
+    let expected = 0u8.wrapping_sub(sum);
+    if cc[0] != expected {
+        return Err(Error::BadChecksum {
+            offset: offset + line.len() - 2,
+            expected: expected as u32,
+            found: cc[0] as u32,
+        });
+    }
+
+    let address = u16::from_be_bytes([body[1], body[2]]);
+    Ok((body[3], address, body[4..].to_vec()))
+}
+
+fn be(data: &[u8], offset: usize, n: usize) -> Result<u32> {
+    if data.len() != n {
+        return Err(Error::Malformed {
+            offset,
+            reason: "wrong length for an address record",
+        });
+    }
+    Ok(data.iter().fold(0, |a, c| a << 8 | *c as u32))
+}
+
+impl Image {
+    /// Parses an Intel HEX file, adjacent data records are merged into
+    /// segments.
+    pub fn parse(text: &[u8]) -> Result<Self> {
+        Ok(Self::parse_prefix(text)?.0)
+    }
+
+    /// Like `parse`, but also tells how many bytes of `text` were used, that
+    /// is up to the end of the EOF record, for hex files embedded in
+    /// something else.
+    pub fn parse_prefix(text: &[u8]) -> Result<(Self, usize)> {
+        let mut image = Image::default();
+        let mut base: u32 = 0;
+        let mut offset = 0;
+
+        for line in text.split(|c| *c == b'\n') {
+            let start = offset;
+            offset += line.len() + 1;
+
+            let line = match line.last() {
+                Some(b'\r') => &line[..line.len() - 1],
+                _ => line,
+            };
+            if line.iter().all(|c| c.is_ascii_whitespace()) {
+                continue;
+            }
+
+            let (kind, address, data) = record(line, start)?;
+            match kind {
+                DATA => {
+                    let address = base.wrapping_add(address as u32);
+                    match image.segments.last_mut() {
+                        Some(s) if s.end() == address => s.data.extend(data),
+                        _ => image.segments.push(Segment {
+                            address,
+                            offset: start,
+                            data,
+                        }),
+                    }
+                }
+                EOF => return Ok((image, offset.min(text.len()))),
+                EXTENDED_SEGMENT_ADDRESS => base = be(&data, start, 2)? << 4,
+                EXTENDED_LINEAR_ADDRESS => base = be(&data, start, 2)? << 16,
+                START_SEGMENT_ADDRESS => {
+                    let cs_ip = be(&data, start, 4)?;
+                    image.start = Some((cs_ip >> 16 << 4) + (cs_ip & 0xffff))
+                }
+                START_LINEAR_ADDRESS => image.start = Some(be(&data, start, 4)?),
+                _ => {
+                    return Err(Error::Malformed {
+                        offset: start,
+                        reason: "unknown record type",
+                    })
+                }
+            }
+        }
+        Ok((image, text.len()))
+    }
+
+    /// `len` bytes at `address`, if a single segment holds them all
+    pub fn get(&self, address: u32, len: usize) -> Option<&[u8]> {
+        self.segments.iter().find_map(|s| {
+            let start = address.checked_sub(s.address)? as usize;
+            s.data.get(start..(start + len))
+        })
+    }
+
+    /// the data of all segments back to back, in file order
+    pub fn concat(&self) -> Vec<u8> {
+        self.segments
+            .iter()
+            .flat_map(|s| s.data.iter().cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static HEX: &[u8] = b":020000041fc01b\r
+:0400000001020304f2\r
+:0400040005060708de\r
+:02000004bfc07b\r
+:0100000042bd\r
+:04000005bfc0000078\r
+:00000001FF\r
+";
+
+    #[test]
+    fn segments() {
+        let image = Image::parse(HEX).unwrap();
+        assert_eq!(image.segments.len(), 2);
+        assert_eq!(image.segments[0].address, 0x1fc0_0000);
+        assert_eq!(image.segments[0].offset, 17);
+        assert_eq!(image.segments[0].data, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(image.segments[1].address, 0xbfc0_0000);
+        assert_eq!(image.segments[1].data, [0x42]);
+        assert_eq!(image.start, Some(0xbfc0_0000));
+        assert_eq!(image.concat(), [1, 2, 3, 4, 5, 6, 7, 8, 0x42]);
+        assert_eq!(image.get(0x1fc0_0002, 4), Some(&[3, 4, 5, 6][..]));
+        assert_eq!(image.get(0x1fc0_0006, 4), None);
+
+        let mut embedded = HEX.to_vec();
+        embedded.extend_from_slice(b"\x00\x01garbage");
+        let (_, len) = Image::parse_prefix(&embedded).unwrap();
+        assert_eq!(len, HEX.len());
+    }
+
+    #[test]
+    fn bad_checksum() {
+        match Image::parse(b":0400000001020304f3\n") {
+            Err(Error::BadChecksum {
+                offset: 17,
+                expected: 0xf2,
+                found: 0xf3,
+            }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        match Image::parse(b":04000000010203\n") {
+            Err(Error::Truncated { offset: 0, .. }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn not_hex() {
+        match Image::parse(b"\n:0400000001020304f2\nhello\n") {
+            Err(Error::BadMagic { offset: 21, .. }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        match Image::parse(b":04000000010z0304f2\n") {
+            Err(Error::Malformed { offset: 12, .. }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::error::Result;
+use memmap::Mmap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::Deref;
+
+/// the file name that means standard input
+pub const STDIN: &str = "-";
+
+/// The bytes of an input file, mapped when we can and read into memory when
+/// we can't: standard input, pipes, process substitution, empty files.
+pub enum Input {
+    Mapped(Mmap),
+    Buffered(Vec<u8>),
+}
+
+impl Input {
+    /// `path` being `-` for standard input
+    pub fn open(path: &str) -> Result<Self> {
+        if path == STDIN {
+            let mut data = Vec::new();
+            io::stdin().lock().read_to_end(&mut data)?;
+            return Ok(Input::Buffered(data));
+        }
+        let mut file = File::open(path)?;
+        if file.metadata()?.is_file() {
+            if let Ok(m) = unsafe { Mmap::map(&file) } {
+                return Ok(Input::Mapped(m));
+            }
+        }
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        Ok(Input::Buffered(data))
+    }
+
+    /// for the tools that can stream instead of holding it all
+    pub fn reader(path: &str) -> Result<Box<dyn Read>> {
+        Ok(match path {
+            STDIN => Box::new(io::stdin()),
+            _ => Box::new(File::open(path)?),
+        })
+    }
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Input::Mapped(m) => m,
+            Input::Buffered(v) => v,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Input {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn files() {
+        let dir = env::temp_dir();
+        let path = dir.join(format!("fwcutter-input-{}", std::process::id()));
+        fs::write(&path, b"mapped").unwrap();
+        let input = Input::open(path.to_str().unwrap()).unwrap();
+        assert!(matches!(input, Input::Mapped(_)));
+        assert_eq!(&*input, b"mapped");
+
+        // can't map an empty file
+        fs::write(&path, b"").unwrap();
+        let input = Input::open(path.to_str().unwrap()).unwrap();
+        assert!(input.is_empty());
+        fs::remove_file(&path).unwrap();
+
+        assert!(Input::open("/nonexistent/fwcutter").is_err());
+    }
+}
@@ -1,4 +1,27 @@
+pub mod arch;
+pub mod baseaddr;
+pub mod bfb;
+pub mod checksum;
 pub mod circular_buffer;
+pub mod cube;
+pub mod cut;
+pub mod diff;
+pub mod entropy;
+pub mod error;
+pub mod fingerprint;
+pub mod ihex;
+pub mod input;
+pub mod manifest;
 pub mod maps;
+pub mod metadata;
+pub mod mips;
 pub mod mpfs;
+pub mod output;
+pub mod patch;
 pub mod pattern;
+pub mod pic32;
+pub mod scan;
+pub mod signature;
+pub mod strings;
+pub mod unpack;
+pub mod xref;
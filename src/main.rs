@@ -1,13 +1,17 @@
 extern crate fwcutter;
+use fwcutter::baseaddr::Endian;
+use fwcutter::error::Result;
+use fwcutter::input::{self, Input};
+use fwcutter::mips::{self, Mode};
+use fwcutter::output::{self, Format};
 use fwcutter::pattern::Pattern;
+use fwcutter::scan;
 
-use memmap::Mmap;
 use std::env;
-use std::fs::File;
 
 static UNIT: [&str; 5] = [" ", "k", "M", "G", "T"];
 
-fn format_size(i: isize) -> String {
+fn format_size(i: usize) -> String {
     let mut c = i;
     for u in UNIT.iter() {
         if (c / 1024) > 0 {
@@ -19,12 +23,20 @@ fn format_size(i: isize) -> String {
     "VALUE TOO BIG".to_string()
 }
 
-fn cut_fw(filename: &str) -> std::io::Result<()> {
-    let file = File::open(filename)?;
-    //    let mut reader = BufReader::with_capacity(1024 * 1024 * 1024, file);
-    //     let mut buf = [0; 1];
-    let mmap = unsafe { Mmap::map(&file)? };
-    let mut reader = mmap.chunks(1);
+/// how many instructions to show after each match, and where the image is
+/// loaded
+struct Disasm {
+    count: usize,
+    base: u32,
+}
+
+fn cut_fw(filename: &str, disasm: Option<&Disasm>, format: Format, first: bool) -> Result<()> {
+    // files are mapped and scanned in parallel, standard input is streamed
+    // unless we disassemble around the matches
+    let data = match (disasm, filename) {
+        (None, input::STDIN) => None,
+        _ => Some(Input::open(filename)?),
+    };
 
     let mut patterns = [
         Pattern::new(&[0x5a, 0x4f, 0x00, 0x00]),
@@ -33,39 +45,80 @@ fn cut_fw(filename: &str) -> std::io::Result<()> {
         //        Pattern::new(b"gr\\").lookback(20),
         Pattern::new(b"\0").lookback(20).repeats(15 * 16 + 7),
     ];
-    let mut lasts = vec![0; patterns.len()];
-    let mut s = vec![String::new(); patterns.len()];
-
-    let mut l = 0;
-    let mut last_addr = 0;
-
-    loop {
-        if let Some(d) = reader.next() {
-            l += 1;
+    let matches = match &data {
+        Some(data) => scan::scan_parallel(data, &mut patterns),
+        None => scan::scan_reader(Input::reader(filename)?, &mut patterns)?,
+    };
 
-            for i in 0..patterns.len() {
-                if let Some(n) = patterns[i].push(d[0]) {
-                    let c = l - n;
-                    let size = l - last_addr;
-                    let format = || format!("{:#016x}: found {:#?}", c, patterns[i]);
-                    if lasts[i] != c {
-                        if lasts[i] == 0 {
-                            s[i] = format()
-                        }
-                        println!("{:#08x} {:>5} {}", size, format_size(size), &s[i]);
-                        last_addr = l;
-                        lasts[i] = c;
+    match format {
+        Format::Json => output::print_json("scan", filename, &matches),
+        Format::Csv => output::print_csv(filename, &matches, first),
+        Format::Text => {
+            let mut last_addr = 0;
+            for m in matches.iter() {
+                // from the end of the previous match to the end of this one
+                let end = m.offset + m.len;
+                let size = end.saturating_sub(last_addr);
+                println!(
+                    "{:#08x} {:>5} {:#016x}: found {}",
+                    size,
+                    format_size(size),
+                    m.offset,
+                    m.describe(patterns[m.pattern].pattern())
+                );
+                last_addr = last_addr.max(end);
+                if let (Some(d), Some(data)) = (disasm, &data) {
+                    let at = m.offset & !3;
+                    for i in
+                        mips::disassemble(data, at, d.base, d.count, Mode::Mips32, Endian::Little)
+                    {
+                        println!("    {}", i);
                     }
-                    s[i] = format();
                 }
             }
-        } else {
-            return Ok(());
+            Ok(())
         }
     }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    cut_fw(&args[1]).unwrap();
+    let mut args: Vec<String> = env::args().collect();
+    let format = Format::from_args(&mut args);
+    let mut disasm = None;
+    if let Some(i) = args.iter().position(|a| a == "--disasm") {
+        let count = args.get(i + 1).and_then(|s| output::number(s));
+        args.drain(i..(i + 2).min(args.len()));
+        let mut base = Some(0);
+        if let Some(i) = args.iter().position(|a| a == "--base") {
+            base = args.get(i + 1).and_then(|s| output::number(s));
+            args.drain(i..(i + 2).min(args.len()));
+        }
+        match (count, base) {
+            (Some(count), Some(base)) => {
+                disasm = Some(Disasm {
+                    count: count as usize,
+                    base,
+                })
+            }
+            _ => args.truncate(1),
+        }
+    }
+    if args.len() < 2 {
+        eprintln!(
+            "usage: {} [--json|--csv] [--disasm count [--base addr]] image...|-",
+            args[0]
+        );
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for (i, filename) in args[1..].iter().enumerate() {
+        if let Err(e) = cut_fw(filename, disasm.as_ref(), format, i == 0) {
+            eprintln!("{}: {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,162 @@
+use crate::diff;
+use crate::error::{Error, Result};
+use crate::output::Row;
+use crate::signature;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// what every extraction writes at the top of its directory
+pub const FILE_NAME: &str = "manifest.json";
+
+/// A file of an extraction directory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// relative to the extraction directory
+    pub path: String,
+    /// where it comes from in its parent
+    pub offset: usize,
+    pub len: usize,
+    /// the file it was cut out of or decoded from, `None` for the source
+    pub parent: Option<String>,
+    pub sha256: String,
+    /// what `signature::identify` makes of it
+    pub kind: Option<String>,
+}
+
+/// Every file produced from `source`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub source: String,
+    pub sha256: String,
+    pub files: Vec<Entry>,
+}
+
+impl Manifest {
+    pub fn new(source: &str, data: &[u8]) -> Self {
+        Manifest {
+            source: source.to_string(),
+            sha256: diff::sha256(data),
+            files: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, path: &str, offset: usize, parent: Option<&str>, data: &[u8]) {
+        self.files.push(Entry {
+            path: path.to_string(),
+            offset,
+            len: data.len(),
+            parent: parent.map(String::from),
+            sha256: diff::sha256(data),
+            kind: signature::identify(data).map(|h| h.kind.name().to_string()),
+        });
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let f = fs::File::create(dir.join(FILE_NAME))?;
+        serde_json::to_writer_pretty(f, self).map_err(io::Error::from)?;
+        Ok(())
+    }
+
+    pub fn read(dir: &Path) -> Result<Self> {
+        let text = fs::read(dir.join(FILE_NAME))?;
+        serde_json::from_slice(&text).map_err(|e| Error::Malformed {
+            offset: e.column(),
+            reason: "not a manifest",
+        })
+    }
+
+    /// Compares the files of `dir` with what was extracted.
+    pub fn verify(&self, dir: &Path) -> Vec<Check> {
+        self.files
+            .iter()
+            .map(|e| {
+                let (status, sha256) = match fs::read(dir.join(&e.path)) {
+                    Err(_) => (Status::Missing, None),
+                    Ok(d) => {
+                        let sha256 = diff::sha256(&d);
+                        match d.len() == e.len && sha256 == e.sha256 {
+                            true => (Status::Ok, Some(sha256)),
+                            false => (Status::Modified, Some(sha256)),
+                        }
+                    }
+                };
+                Check {
+                    path: e.path.clone(),
+                    status,
+                    sha256,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Missing,
+    Modified,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Missing => "missing",
+            Status::Modified => "modified",
+        }
+    }
+}
+
+/// A manifest entry checked against the directory.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Check {
+    pub path: String,
+    pub status: Status,
+    /// of what's there now
+    pub sha256: Option<String>,
+}
+
+impl Row for Check {
+    const HEADER: &'static [&'static str] = &["path", "status", "sha256"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.path.clone(),
+            self.status.name().to_string(),
+            self.sha256.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn round_trip() {
+        let dir = env::temp_dir().join(format!("fwcutter-manifest-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a")).unwrap();
+        let mut m = Manifest::new("firmware.ar", b"whole archive");
+        for (path, data) in [
+            ("a/one", &b"first"[..]),
+            ("two", b"second"),
+            ("three", b"3"),
+        ] {
+            fs::write(dir.join(path), data).unwrap();
+            m.add(path, 10, None, data);
+        }
+        m.write(&dir).unwrap();
+
+        let read = Manifest::read(&dir).unwrap();
+        assert_eq!(read, m);
+        fs::write(dir.join("two"), b"changed").unwrap();
+        fs::remove_file(dir.join("three")).unwrap();
+        let status: Vec<Status> = read.verify(&dir).iter().map(|c| c.status).collect();
+        assert_eq!(status, [Status::Ok, Status::Modified, Status::Missing]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,24 +1,61 @@
+use crate::error::{Error, Result};
 use memmap::Mmap;
 use std::cmp;
 
-pub struct ReadableMmap {
-    m: Mmap,
+/// Anything that can be seen as bytes can be read, not only mmaps, so we
+/// can parse slices of an already mapped archive.
+pub struct ReadableMmap<T: AsRef<[u8]> = Mmap> {
+    m: T,
     p: usize,
 }
 
-impl ReadableMmap {
-    pub fn new(m: Mmap) -> Self {
+impl<T: AsRef<[u8]>> ReadableMmap<T> {
+    pub fn new(m: T) -> Self {
         ReadableMmap { m, p: 0 }
     }
-    pub fn seek(&mut self, s: isize) {
-        self.p = (self.p as isize + s) as usize
+
+    pub fn position(&self) -> usize {
+        self.p
+    }
+
+    pub fn len(&self) -> usize {
+        self.m.as_ref().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.m.as_ref().is_empty()
+    }
+
+    pub fn seek(&mut self, s: isize) -> Result<()> {
+        let p = self.p as isize + s;
+        if p < 0 || p as usize > self.m.as_ref().len() {
+            return Err(Error::InvalidOffset {
+                offset: p.max(0) as usize,
+                len: self.m.as_ref().len(),
+            });
+        }
+        self.p = p as usize;
+        Ok(())
+    }
+
+    /// make sure there are at least `n` bytes left to read
+    pub fn require(&self, n: usize) -> Result<()> {
+        let left = self.m.as_ref().len() - self.p;
+        if left < n {
+            return Err(Error::Truncated {
+                offset: self.p,
+                needed: n - left,
+            });
+        }
+        Ok(())
     }
 }
 
-impl std::io::Read for ReadableMmap {
+impl<T: AsRef<[u8]>> std::io::Read for ReadableMmap<T> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let len = cmp::min(self.m.len() - self.p, buf.len());
-        buf.copy_from_slice(&self.m[self.p..(self.p + len)]);
+        let m = self.m.as_ref();
+        let len = cmp::min(m.len() - self.p, buf.len());
+        buf[..len].copy_from_slice(&m[self.p..(self.p + len)]);
 
         self.p += len;
         Ok(len)
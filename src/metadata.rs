@@ -0,0 +1,323 @@
+use crate::cut;
+use crate::error::Result;
+use crate::ihex;
+use crate::output::Row;
+use crate::strings::{self, Encoding};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// shortest printable run we look at
+pub const MIN_LEN: usize = 4;
+
+static MONTHS: &[&str] = &[
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// strings that give away the toolchain a binary was built with
+static BANNERS: &[&str] = &["GCC:", "GNU C", "(GNU)", "XC32", "MPLAB", "Microchip"];
+
+/// words that start a model identifier
+static MODELS: &[&str] = &["PIC32MX", "PIC32MZ", "Cube"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Version,
+    Date,
+    Compiler,
+    Model,
+}
+
+/// Something we found, `offset` is where the string holding it starts.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Item {
+    pub kind: Kind,
+    pub offset: usize,
+    pub value: String,
+}
+
+fn at_word_start(s: &str, i: usize) -> bool {
+    i == 0 || !s.as_bytes()[i - 1].is_ascii_alphanumeric()
+}
+
+/// `1.14B`, `2.0.3` at the start of `s`: digits and dots with at least one
+/// dot, and maybe a letter
+fn version_number(s: &str) -> Option<&str> {
+    let b = s.as_bytes();
+    let mut end = b
+        .iter()
+        .position(|c| !(c.is_ascii_digit() || *c == b'.'))
+        .unwrap_or(b.len());
+    while end > 0 && b[end - 1] == b'.' {
+        end -= 1;
+    }
+    if end == 0 || !b[0].is_ascii_digit() || !s[..end].contains('.') {
+        return None;
+    }
+    if end < b.len()
+        && b[end].is_ascii_alphabetic()
+        && b.get(end + 1).is_none_or(|c| !c.is_ascii_alphanumeric())
+    {
+        end += 1;
+    }
+    Some(&s[..end])
+}
+
+/// `V1.14B`, `v2.0` or `version 1.2`
+fn versions(s: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    let lower = s.to_ascii_lowercase();
+    for (i, _) in lower.match_indices("version") {
+        let rest = s[(i + 7)..].trim_start_matches([' ', ':', '=', '\t']);
+        let rest = rest.trim_start_matches(['v', 'V']);
+        if let Some(v) = version_number(rest) {
+            found.push(v.to_string());
+        }
+    }
+    for (i, _) in lower.match_indices('v') {
+        if !at_word_start(s, i) {
+            continue;
+        }
+        if let Some(v) = version_number(&s[(i + 1)..]) {
+            let v = format!("{}{}", &s[i..(i + 1)], v);
+            if !found.iter().any(|f| v.ends_with(f.as_str())) {
+                found.push(v);
+            }
+        }
+    }
+    found
+}
+
+fn digits(s: &str, n: usize) -> bool {
+    s.len() == n && s.bytes().all(|c| c.is_ascii_digit())
+}
+
+/// `__DATE__` style `Jan 12 2014` and ISO `2014-01-12`
+fn dates(s: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    for m in MONTHS {
+        for (i, _) in s.match_indices(m) {
+            let rest = &s[(i + 3)..];
+            let mut words = rest.split(' ').filter(|w| !w.is_empty());
+            let (day, year) = match (words.next(), words.next()) {
+                (Some(d), Some(y)) => (d, y.get(..4).unwrap_or(y)),
+                _ => continue,
+            };
+            if !rest.starts_with(' ') || !(digits(day, 1) || digits(day, 2)) || !digits(year, 4) {
+                continue;
+            }
+            found.push(format!("{} {} {}", m, day, year));
+        }
+    }
+    let b = s.as_bytes();
+    for i in 0..b.len().saturating_sub(9) {
+        let d = match s.get(i..(i + 10)) {
+            Some(d) if d.is_ascii() => d,
+            _ => continue,
+        };
+        if digits(&d[0..4], 4)
+            && digits(&d[5..7], 2)
+            && digits(&d[8..10], 2)
+            && b[i + 4] == b'-'
+            && b[i + 7] == b'-'
+            && (1..=12).contains(&d[5..7].parse::<u8>().unwrap_or(0))
+            && (1..=31).contains(&d[8..10].parse::<u8>().unwrap_or(0))
+        {
+            found.push(d.to_string());
+        }
+    }
+    found
+}
+
+fn models(s: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    for m in MODELS {
+        for (i, _) in s.match_indices(m) {
+            if !at_word_start(s, i) {
+                continue;
+            }
+            let end = s[i..]
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .map_or(s.len(), |n| i + n);
+            // `Cube3` and `CubePro` but not `Cubed`
+            let next = s.as_bytes().get(i + m.len());
+            if next.is_some_and(|c| c.is_ascii_lowercase()) {
+                continue;
+            }
+            found.push(s[i..end].to_string());
+        }
+    }
+    found
+}
+
+/// Looks for versions, dates, compiler banners and model ids in the strings
+/// of `data`.
+pub fn analyse(data: &[u8]) -> Vec<Item> {
+    let mut items = Vec::new();
+    for string in strings::find(data, MIN_LEN, Encoding::ALL) {
+        let (offset, s) = (string.offset, string.value.as_str());
+        let mut push = |kind, values: Vec<String>| {
+            items.extend(values.into_iter().map(|value| Item {
+                kind,
+                offset,
+                value,
+            }))
+        };
+        push(Kind::Version, versions(s));
+        push(Kind::Date, dates(s));
+        if BANNERS.iter().any(|b| s.contains(b)) {
+            push(Kind::Compiler, vec![s.trim().to_string()]);
+        }
+        push(Kind::Model, models(s));
+    }
+    items
+}
+
+/// the build number in names like `Image695.hex`: the last run of digits
+pub fn build_number(path: &str) -> Option<u32> {
+    let name = path.rsplit('/').next()?;
+    let stem = name.split('.').next()?;
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    stem[start..end].parse().ok()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MemberMetadata {
+    pub path: String,
+    pub build: Option<u32>,
+    /// set when the member is Intel HEX and `items` offsets are in its
+    /// decoded data rather than the text
+    pub decoded: bool,
+    pub items: Vec<Item>,
+}
+
+impl MemberMetadata {
+    pub fn new(path: &str, data: &[u8]) -> Self {
+        let image = if path.to_ascii_lowercase().ends_with(".hex") {
+            ihex::Image::parse(data).ok().map(|i| i.concat())
+        } else {
+            None
+        };
+        MemberMetadata {
+            path: path.to_string(),
+            build: build_number(path),
+            decoded: image.is_some(),
+            items: analyse(image.as_deref().unwrap_or(data)),
+        }
+    }
+
+    /// the distinct values of one kind
+    pub fn values(&self, kind: Kind) -> BTreeSet<&str> {
+        self.items
+            .iter()
+            .filter(|i| i.kind == kind)
+            .map(|i| i.value.as_str())
+            .collect()
+    }
+}
+
+impl Row for MemberMetadata {
+    const HEADER: &'static [&'static str] =
+        &["path", "build", "versions", "dates", "compilers", "models"];
+
+    fn row(&self) -> Vec<String> {
+        let join = |kind| self.values(kind).into_iter().collect::<Vec<_>>().join(";");
+        vec![
+            self.path.clone(),
+            self.build.map(|b| b.to_string()).unwrap_or_default(),
+            join(Kind::Version),
+            join(Kind::Date),
+            join(Kind::Compiler),
+            join(Kind::Model),
+        ]
+    }
+}
+
+/// What a whole archive tells about itself, the distinct values found
+/// across its members, then the details member by member.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Summary {
+    pub builds: BTreeSet<u32>,
+    pub versions: BTreeSet<String>,
+    pub dates: BTreeSet<String>,
+    pub compilers: BTreeSet<String>,
+    pub models: BTreeSet<String>,
+    pub members: Vec<MemberMetadata>,
+}
+
+/// Analyses every member of a `firmware.ar`.
+pub fn archive(data: &[u8]) -> Result<Summary> {
+    let members: Vec<MemberMetadata> = cut::split(data)?
+        .iter()
+        .map(|m| MemberMetadata::new(&m.path, m.data(data)))
+        .collect();
+    let all = |kind| {
+        members
+            .iter()
+            .flat_map(|m| m.values(kind))
+            .map(String::from)
+            .collect()
+    };
+    Ok(Summary {
+        builds: members.iter().filter_map(|m| m.build).collect(),
+        versions: all(Kind::Version),
+        dates: all(Kind::Date),
+        compilers: all(Kind::Compiler),
+        models: all(Kind::Model),
+        members,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(items: &[Item], kind: Kind) -> Vec<&str> {
+        items
+            .iter()
+            .filter(|i| i.kind == kind)
+            .map(|i| i.value.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn found() {
+        let data = b"\x00\x01Firmware V1.14B\x00ab\x00Build Jan  5 2014 12:00:00\x00\
+            GCC: (Microchip Technology) 4.5.2 MPLAB XC32 Compiler v1.31\x00\x7f\
+            PIC32MX795F512L on a Cube3\x00version: 2.0.3\x00date 2013-11-30\x00";
+        let items = analyse(data);
+        assert_eq!(values(&items, Kind::Version), ["V1.14B", "v1.31", "2.0.3"]);
+        assert_eq!(values(&items, Kind::Date), ["Jan 5 2014", "2013-11-30"]);
+        assert_eq!(values(&items, Kind::Model), ["PIC32MX795F512L", "Cube3"]);
+        let compilers = values(&items, Kind::Compiler);
+        assert_eq!(compilers.len(), 1);
+        assert!(compilers[0].starts_with("GCC: (Microchip"));
+        assert_eq!(items[0].offset, 2);
+    }
+
+    #[test]
+    fn nothing() {
+        assert!(analyse(b"vvv v. version x 2013-13-01 Cubed\x00").is_empty());
+        assert!(versions("abc1.2 av1.0 1.2").is_empty());
+    }
+
+    #[test]
+    fn builds() {
+        assert_eq!(build_number("Image695.hex"), Some(695));
+        assert_eq!(build_number("fw/v2/Image_12a.bin"), Some(12));
+        assert_eq!(build_number("web.bin"), None);
+    }
+
+    #[test]
+    fn summary() {
+        let ar = cut::archive(&[("Image695.bin", b"\x00Firmware V1.14B\x00")]);
+        let summary = archive(&ar).unwrap();
+        assert_eq!(summary.builds.iter().collect::<Vec<_>>(), [&695]);
+        assert_eq!(summary.versions.iter().collect::<Vec<_>>(), ["V1.14B"]);
+        assert_eq!(summary.members[0].path, "Image695.bin");
+        assert!(!summary.members[0].decoded);
+    }
+}
@@ -0,0 +1,784 @@
+use crate::baseaddr::Endian;
+use crate::output::Row;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// o32 register names
+pub static REGISTERS: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+    "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp", "fp",
+    "ra",
+];
+
+/// the 8 registers a 3 bit MIPS16e field can name
+static REGISTERS16: [usize; 8] = [16, 17, 2, 3, 4, 5, 6, 7];
+
+/// The PIC32MX core runs MIPS32 and MIPS16e, microMIPS only came with the
+/// MZ parts and isn't handled.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Mips32,
+    Mips16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Flow {
+    /// goes on with the next instruction
+    Next,
+    /// conditional, `target` or the next one
+    Branch,
+    /// always goes to `target`, or somewhere in a register
+    Jump,
+    /// a function call, comes back after the delay slot
+    Call,
+    /// `jr ra`
+    Return,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Instruction {
+    pub address: u32,
+    pub offset: usize,
+    /// 2 or 4 bytes, 4 for extended MIPS16e instructions too
+    pub len: usize,
+    pub raw: u32,
+    pub mnemonic: &'static str,
+    pub operands: String,
+    pub flow: Flow,
+    /// where a branch, jump or call goes, when it's known
+    pub target: Option<u32>,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let raw = match self.len {
+            2 => format!("{:04x}    ", self.raw),
+            _ => format!("{:08x}", self.raw),
+        };
+        let line = format!(
+            "{:#010x}: {}  {:<8}{}",
+            self.address, raw, self.mnemonic, self.operands
+        );
+        f.write_str(line.trim_end())
+    }
+}
+
+impl Row for Instruction {
+    const HEADER: &'static [&'static str] =
+        &["address", "offset", "raw", "mnemonic", "operands", "target"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            format!("{:#010x}", self.address),
+            self.offset.to_string(),
+            format!("{:x}", self.raw),
+            self.mnemonic.to_string(),
+            self.operands.clone(),
+            self.target
+                .map(|t| format!("{:#010x}", t))
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+fn reg(n: u32) -> &'static str {
+    REGISTERS[(n & 0x1f) as usize]
+}
+
+fn reg16(n: u32) -> &'static str {
+    REGISTERS[REGISTERS16[(n & 7) as usize]]
+}
+
+fn sext(value: u32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    ((value << shift) as i32) >> shift
+}
+
+/// what we decoded, before it gets an address
+struct Decoded {
+    mnemonic: &'static str,
+    operands: String,
+    flow: Flow,
+    target: Option<u32>,
+}
+
+fn op(mnemonic: &'static str, operands: String) -> Decoded {
+    Decoded {
+        mnemonic,
+        operands,
+        flow: Flow::Next,
+        target: None,
+    }
+}
+
+fn flow(mnemonic: &'static str, operands: String, flow: Flow, target: Option<u32>) -> Decoded {
+    Decoded {
+        mnemonic,
+        operands,
+        flow,
+        target,
+    }
+}
+
+fn unknown() -> Decoded {
+    op(".word", String::new())
+}
+
+/// Decodes the MIPS32 instruction `w` sitting at `address`.
+fn decode32(w: u32, address: u32) -> Decoded {
+    let opcode = w >> 26;
+    let rs = (w >> 21) & 0x1f;
+    let rt = (w >> 16) & 0x1f;
+    let rd = (w >> 11) & 0x1f;
+    let sa = (w >> 6) & 0x1f;
+    let funct = w & 0x3f;
+    let imm = w & 0xffff;
+    let simm = sext(imm, 16);
+    let branch = address.wrapping_add(4).wrapping_add((simm << 2) as u32);
+    let jump = (address.wrapping_add(4) & 0xf000_0000) | ((w & 0x03ff_ffff) << 2);
+
+    let rrr = |m| op(m, format!("{}, {}, {}", reg(rd), reg(rs), reg(rt)));
+    let rri = |m| op(m, format!("{}, {}, {}", reg(rt), reg(rs), simm));
+    let rrx = |m| op(m, format!("{}, {}, {:#x}", reg(rt), reg(rs), imm));
+    let mem = |m| op(m, format!("{}, {}({})", reg(rt), simm, reg(rs)));
+    let cond1 = |m| {
+        flow(
+            m,
+            format!("{}, {:#x}", reg(rs), branch),
+            Flow::Branch,
+            Some(branch),
+        )
+    };
+    let cond2 = |m| {
+        flow(
+            m,
+            format!("{}, {}, {:#x}", reg(rs), reg(rt), branch),
+            Flow::Branch,
+            Some(branch),
+        )
+    };
+
+    match opcode {
+        0 => match funct {
+            0 if w == 0 => op("nop", String::new()),
+            0 if w == 0x40 => op("ssnop", String::new()),
+            0 if w == 0xc0 => op("ehb", String::new()),
+            0 => op("sll", format!("{}, {}, {}", reg(rd), reg(rt), sa)),
+            2 if rs == 1 => op("rotr", format!("{}, {}, {}", reg(rd), reg(rt), sa)),
+            2 => op("srl", format!("{}, {}, {}", reg(rd), reg(rt), sa)),
+            3 => op("sra", format!("{}, {}, {}", reg(rd), reg(rt), sa)),
+            4 => op("sllv", format!("{}, {}, {}", reg(rd), reg(rt), reg(rs))),
+            6 if sa == 1 => op("rotrv", format!("{}, {}, {}", reg(rd), reg(rt), reg(rs))),
+            6 => op("srlv", format!("{}, {}, {}", reg(rd), reg(rt), reg(rs))),
+            7 => op("srav", format!("{}, {}, {}", reg(rd), reg(rt), reg(rs))),
+            8 if rs == 31 => flow("jr", reg(rs).to_string(), Flow::Return, None),
+            8 => flow("jr", reg(rs).to_string(), Flow::Jump, None),
+            9 if rd == 31 => flow("jalr", reg(rs).to_string(), Flow::Call, None),
+            9 => flow(
+                "jalr",
+                format!("{}, {}", reg(rd), reg(rs)),
+                Flow::Call,
+                None,
+            ),
+            10 => rrr("movz"),
+            11 => rrr("movn"),
+            12 => op("syscall", String::new()),
+            13 => op("break", String::new()),
+            15 => op("sync", String::new()),
+            16 => op("mfhi", reg(rd).to_string()),
+            17 => op("mthi", reg(rs).to_string()),
+            18 => op("mflo", reg(rd).to_string()),
+            19 => op("mtlo", reg(rs).to_string()),
+            24 => op("mult", format!("{}, {}", reg(rs), reg(rt))),
+            25 => op("multu", format!("{}, {}", reg(rs), reg(rt))),
+            26 => op("div", format!("{}, {}", reg(rs), reg(rt))),
+            27 => op("divu", format!("{}, {}", reg(rs), reg(rt))),
+            32 => rrr("add"),
+            33 if rt == 0 => op("move", format!("{}, {}", reg(rd), reg(rs))),
+            33 => rrr("addu"),
+            34 => rrr("sub"),
+            35 if rs == 0 => op("negu", format!("{}, {}", reg(rd), reg(rt))),
+            35 => rrr("subu"),
+            36 => rrr("and"),
+            37 if rt == 0 => op("move", format!("{}, {}", reg(rd), reg(rs))),
+            37 => rrr("or"),
+            38 => rrr("xor"),
+            39 => rrr("nor"),
+            42 => rrr("slt"),
+            43 => rrr("sltu"),
+            52 => op("teq", format!("{}, {}", reg(rs), reg(rt))),
+            _ => unknown(),
+        },
+        1 => match rt {
+            0 => cond1("bltz"),
+            1 if rs == 0 => flow("b", format!("{:#x}", branch), Flow::Jump, Some(branch)),
+            1 => cond1("bgez"),
+            2 => cond1("bltzl"),
+            3 => cond1("bgezl"),
+            16 => flow(
+                "bltzal",
+                format!("{}, {:#x}", reg(rs), branch),
+                Flow::Call,
+                Some(branch),
+            ),
+            17 if rs == 0 => flow("bal", format!("{:#x}", branch), Flow::Call, Some(branch)),
+            17 => flow(
+                "bgezal",
+                format!("{}, {:#x}", reg(rs), branch),
+                Flow::Call,
+                Some(branch),
+            ),
+            _ => unknown(),
+        },
+        2 => flow("j", format!("{:#x}", jump), Flow::Jump, Some(jump)),
+        3 => flow("jal", format!("{:#x}", jump), Flow::Call, Some(jump)),
+        4 if rs == 0 && rt == 0 => flow("b", format!("{:#x}", branch), Flow::Jump, Some(branch)),
+        4 if rt == 0 => flow(
+            "beqz",
+            format!("{}, {:#x}", reg(rs), branch),
+            Flow::Branch,
+            Some(branch),
+        ),
+        4 => cond2("beq"),
+        5 if rt == 0 => flow(
+            "bnez",
+            format!("{}, {:#x}", reg(rs), branch),
+            Flow::Branch,
+            Some(branch),
+        ),
+        5 => cond2("bne"),
+        6 => cond1("blez"),
+        7 => cond1("bgtz"),
+        8 => rri("addi"),
+        9 if rs == 0 => op("li", format!("{}, {}", reg(rt), simm)),
+        9 => rri("addiu"),
+        10 => rri("slti"),
+        11 => rri("sltiu"),
+        12 => rrx("andi"),
+        13 if rs == 0 => op("li", format!("{}, {:#x}", reg(rt), imm)),
+        13 => rrx("ori"),
+        14 => rrx("xori"),
+        15 => op("lui", format!("{}, {:#x}", reg(rt), imm)),
+        16 => match rs {
+            0 => op("mfc0", format!("{}, ${}, {}", reg(rt), rd, w & 7)),
+            4 => op("mtc0", format!("{}, ${}, {}", reg(rt), rd, w & 7)),
+            11 if funct == 0 && (w >> 5) & 1 == 0 => op("di", reg(rt).to_string()),
+            11 => op("ei", reg(rt).to_string()),
+            16 if funct == 0x18 => flow("eret", String::new(), Flow::Return, None),
+            16 if funct == 0x20 => op("wait", String::new()),
+            _ => unknown(),
+        },
+        20 => cond2("beql"),
+        21 => cond2("bnel"),
+        22 => cond1("blezl"),
+        23 => cond1("bgtzl"),
+        28 => match funct {
+            0 => op("madd", format!("{}, {}", reg(rs), reg(rt))),
+            1 => op("maddu", format!("{}, {}", reg(rs), reg(rt))),
+            2 => rrr("mul"),
+            4 => op("msub", format!("{}, {}", reg(rs), reg(rt))),
+            5 => op("msubu", format!("{}, {}", reg(rs), reg(rt))),
+            32 => op("clz", format!("{}, {}", reg(rd), reg(rs))),
+            33 => op("clo", format!("{}, {}", reg(rd), reg(rs))),
+            63 => op("sdbbp", String::new()),
+            _ => unknown(),
+        },
+        31 => match funct {
+            0 => op(
+                "ext",
+                format!("{}, {}, {}, {}", reg(rt), reg(rs), sa, rd + 1),
+            ),
+            // msb below lsb isn't an instruction
+            4 => match (rd + 1).checked_sub(sa) {
+                Some(size) => op("ins", format!("{}, {}, {}, {}", reg(rt), reg(rs), sa, size)),
+                None => unknown(),
+            },
+            32 => match sa {
+                2 => op("wsbh", format!("{}, {}", reg(rd), reg(rt))),
+                16 => op("seb", format!("{}, {}", reg(rd), reg(rt))),
+                24 => op("seh", format!("{}, {}", reg(rd), reg(rt))),
+                _ => unknown(),
+            },
+            _ => unknown(),
+        },
+        32 => mem("lb"),
+        33 => mem("lh"),
+        34 => mem("lwl"),
+        35 => mem("lw"),
+        36 => mem("lbu"),
+        37 => mem("lhu"),
+        38 => mem("lwr"),
+        40 => mem("sb"),
+        41 => mem("sh"),
+        42 => mem("swl"),
+        43 => mem("sw"),
+        46 => mem("swr"),
+        47 => op("cache", format!("{:#x}, {}({})", rt, simm, reg(rs))),
+        48 => mem("ll"),
+        51 => op("pref", format!("{}, {}({})", rt, simm, reg(rs))),
+        56 => mem("sc"),
+        _ => unknown(),
+    }
+}
+
+/// Decodes the MIPS16e instruction `h` at `address`, `ext` being the
+/// halfword that follows (for EXTEND and JAL). Returns the length too.
+fn decode16(h: u32, ext: Option<u32>, address: u32) -> (Decoded, usize) {
+    let major = h >> 11;
+
+    // JAL and JALX are 32 bits, the target spread over both halfwords
+    if major == 3 {
+        let low = match ext {
+            Some(l) => l,
+            None => return (unknown(), 2),
+        };
+        let index = ((h & 0x1f) << 21) | (((h >> 5) & 0x1f) << 16) | low;
+        let target = (address.wrapping_add(4) & 0xf000_0000) | (index << 2);
+        let m = if (h >> 10) & 1 == 1 { "jalx" } else { "jal" };
+        return (
+            flow(m, format!("{:#x}", target), Flow::Call, Some(target)),
+            4,
+        );
+    }
+
+    if major == 0x1e {
+        let next = match ext {
+            Some(n) => n,
+            None => return (unknown(), 2),
+        };
+        return (decode16_extended(h, next, address.wrapping_add(2)), 4);
+    }
+
+    let rx = (h >> 8) & 7;
+    let ry = (h >> 5) & 7;
+    let imm8 = h & 0xff;
+    let pc = address.wrapping_add(2);
+    let d = match major {
+        0 => op("addiu", format!("{}, sp, {}", reg16(rx), imm8 << 2)),
+        1 => op("addiu", format!("{}, pc, {}", reg16(rx), imm8 << 2)),
+        2 => {
+            let t = pc.wrapping_add((sext(h & 0x7ff, 11) << 1) as u32);
+            flow("b", format!("{:#x}", t), Flow::Jump, Some(t))
+        }
+        4 | 5 => {
+            let t = pc.wrapping_add((sext(imm8, 8) << 1) as u32);
+            let m = if major == 4 { "beqz" } else { "bnez" };
+            flow(m, format!("{}, {:#x}", reg16(rx), t), Flow::Branch, Some(t))
+        }
+        6 => {
+            let sa = match (h >> 2) & 7 {
+                0 => 8,
+                n => n,
+            };
+            let m = match h & 3 {
+                0 => "sll",
+                2 => "srl",
+                3 => "sra",
+                _ => return (unknown(), 2),
+            };
+            op(m, format!("{}, {}, {}", reg16(rx), reg16(ry), sa))
+        }
+        8 if (h >> 4) & 1 == 0 => op(
+            "addiu",
+            format!("{}, {}, {}", reg16(ry), reg16(rx), sext(h & 0xf, 4)),
+        ),
+        9 => op("addiu", format!("{}, {}", reg16(rx), sext(imm8, 8))),
+        10 => op("slti", format!("{}, {}", reg16(rx), imm8)),
+        11 => op("sltiu", format!("{}, {}", reg16(rx), imm8)),
+        12 => decode16_i8(h, pc),
+        13 => op("li", format!("{}, {}", reg16(rx), imm8)),
+        14 => op("cmpi", format!("{}, {}", reg16(rx), imm8)),
+        16 | 17 | 19 | 20 | 21 | 24 | 25 | 27 => {
+            let (m, scale) = match major {
+                16 => ("lb", 1),
+                17 => ("lh", 2),
+                19 => ("lw", 4),
+                20 => ("lbu", 1),
+                21 => ("lhu", 2),
+                24 => ("sb", 1),
+                25 => ("sh", 2),
+                _ => ("sw", 4),
+            };
+            op(
+                m,
+                format!("{}, {}({})", reg16(ry), (h & 0x1f) * scale, reg16(rx)),
+            )
+        }
+        18 => op("lw", format!("{}, {}(sp)", reg16(rx), imm8 << 2)),
+        22 => op("lw", format!("{}, {}(pc)", reg16(rx), imm8 << 2)),
+        26 => op("sw", format!("{}, {}(sp)", reg16(rx), imm8 << 2)),
+        28 => {
+            let m = match h & 3 {
+                1 => "addu",
+                3 => "subu",
+                _ => return (unknown(), 2),
+            };
+            op(
+                m,
+                format!("{}, {}, {}", reg16((h >> 2) & 7), reg16(rx), reg16(ry)),
+            )
+        }
+        29 => decode16_rr(h),
+        _ => unknown(),
+    };
+    (d, 2)
+}
+
+fn decode16_i8(h: u32, pc: u32) -> Decoded {
+    let imm8 = h & 0xff;
+    match (h >> 8) & 7 {
+        0 | 1 => {
+            let t = pc.wrapping_add((sext(imm8, 8) << 1) as u32);
+            let m = if (h >> 8) & 7 == 0 { "bteqz" } else { "btnez" };
+            flow(m, format!("{:#x}", t), Flow::Branch, Some(t))
+        }
+        2 => op("sw", format!("ra, {}(sp)", imm8 << 2)),
+        3 => op("addiu", format!("sp, {}", sext(imm8, 8) << 3)),
+        4 => {
+            let mut regs = Vec::new();
+            if (h >> 6) & 1 == 1 {
+                regs.push("ra");
+            }
+            if (h >> 5) & 1 == 1 {
+                regs.push("s0");
+            }
+            if (h >> 4) & 1 == 1 {
+                regs.push("s1");
+            }
+            let frame = match h & 0xf {
+                0 => 128,
+                n => n << 3,
+            };
+            let m = if (h >> 7) & 1 == 1 { "save" } else { "restore" };
+            regs.push("");
+            op(m, format!("{}{}", regs.join(", "), frame))
+        }
+        5 => {
+            let r32 = ((h >> 5) & 7) | (((h >> 3) & 3) << 3);
+            op("move", format!("{}, {}", reg(r32), reg16(h & 7)))
+        }
+        7 => op(
+            "move",
+            format!("{}, {}", reg16((h >> 5) & 7), reg(h & 0x1f)),
+        ),
+        _ => unknown(),
+    }
+}
+
+fn decode16_rr(h: u32) -> Decoded {
+    let rx = (h >> 8) & 7;
+    let ry = (h >> 5) & 7;
+    let two = |m| op(m, format!("{}, {}", reg16(rx), reg16(ry)));
+    match h & 0x1f {
+        0 => match ry {
+            0 => flow("jr", reg16(rx).to_string(), Flow::Jump, None),
+            1 => flow("jr", "ra".to_string(), Flow::Return, None),
+            2 => flow("jalr", reg16(rx).to_string(), Flow::Call, None),
+            4 => flow("jrc", reg16(rx).to_string(), Flow::Jump, None),
+            5 => flow("jrc", "ra".to_string(), Flow::Return, None),
+            6 => flow("jalrc", reg16(rx).to_string(), Flow::Call, None),
+            _ => unknown(),
+        },
+        1 => op("sdbbp", String::new()),
+        2 => two("slt"),
+        3 => two("sltu"),
+        4 => op("sllv", format!("{}, {}", reg16(ry), reg16(rx))),
+        5 => op("break", String::new()),
+        6 => op("srlv", format!("{}, {}", reg16(ry), reg16(rx))),
+        7 => op("srav", format!("{}, {}", reg16(ry), reg16(rx))),
+        10 => two("cmp"),
+        11 => two("neg"),
+        12 => two("and"),
+        13 => two("or"),
+        14 => two("xor"),
+        15 => two("not"),
+        16 => op("mfhi", reg16(rx).to_string()),
+        17 => match ry {
+            0 => op("zeb", reg16(rx).to_string()),
+            1 => op("zeh", reg16(rx).to_string()),
+            4 => op("seb", reg16(rx).to_string()),
+            5 => op("seh", reg16(rx).to_string()),
+            _ => unknown(),
+        },
+        18 => op("mflo", reg16(rx).to_string()),
+        24 => two("mult"),
+        25 => two("multu"),
+        26 => two("div"),
+        27 => two("divu"),
+        _ => unknown(),
+    }
+}
+
+/// EXTEND `e` followed by `h`: the usual immediates become 16 bits, and
+/// aren't scaled any more. `pc` is the address of `h`.
+fn decode16_extended(e: u32, h: u32, pc: u32) -> Decoded {
+    let imm = sext(
+        ((e & 0x1f) << 11) | (((e >> 5) & 0x3f) << 5) | (h & 0x1f),
+        16,
+    );
+    let rx = (h >> 8) & 7;
+    let ry = (h >> 5) & 7;
+    let target = pc.wrapping_add(2).wrapping_add((imm << 1) as u32);
+    match h >> 11 {
+        0 => op("addiu", format!("{}, sp, {}", reg16(rx), imm)),
+        1 => op("addiu", format!("{}, pc, {}", reg16(rx), imm)),
+        2 => flow("b", format!("{:#x}", target), Flow::Jump, Some(target)),
+        4 | 5 => {
+            let m = if h >> 11 == 4 { "beqz" } else { "bnez" };
+            flow(
+                m,
+                format!("{}, {:#x}", reg16(rx), target),
+                Flow::Branch,
+                Some(target),
+            )
+        }
+        6 => {
+            let m = match h & 3 {
+                0 => "sll",
+                2 => "srl",
+                3 => "sra",
+                _ => return unknown(),
+            };
+            op(
+                m,
+                format!("{}, {}, {}", reg16(rx), reg16(ry), (e >> 6) & 0x1f),
+            )
+        }
+        8 => {
+            let imm15 = sext(((e & 0xf) << 11) | (((e >> 4) & 0x7f) << 4) | (h & 0xf), 15);
+            op("addiu", format!("{}, {}, {}", reg16(ry), reg16(rx), imm15))
+        }
+        9 => op("addiu", format!("{}, {}", reg16(rx), imm)),
+        10 => op("slti", format!("{}, {}", reg16(rx), imm)),
+        11 => op("sltiu", format!("{}, {}", reg16(rx), imm)),
+        12 if (h >> 8) & 7 == 3 => op("addiu", format!("sp, {}", imm)),
+        13 => op("li", format!("{}, {}", reg16(rx), imm as u16)),
+        14 => op("cmpi", format!("{}, {}", reg16(rx), imm as u16)),
+        16 => op("lb", format!("{}, {}({})", reg16(ry), imm, reg16(rx))),
+        17 => op("lh", format!("{}, {}({})", reg16(ry), imm, reg16(rx))),
+        18 => op("lw", format!("{}, {}(sp)", reg16(rx), imm)),
+        19 => op("lw", format!("{}, {}({})", reg16(ry), imm, reg16(rx))),
+        20 => op("lbu", format!("{}, {}({})", reg16(ry), imm, reg16(rx))),
+        21 => op("lhu", format!("{}, {}({})", reg16(ry), imm, reg16(rx))),
+        22 => op("lw", format!("{}, {}(pc)", reg16(rx), imm)),
+        24 => op("sb", format!("{}, {}({})", reg16(ry), imm, reg16(rx))),
+        25 => op("sh", format!("{}, {}({})", reg16(ry), imm, reg16(rx))),
+        26 => op("sw", format!("{}, {}(sp)", reg16(rx), imm)),
+        27 => op("sw", format!("{}, {}({})", reg16(ry), imm, reg16(rx))),
+        _ => unknown(),
+    }
+}
+
+/// Disassembles up to `count` instructions of `data` from `offset`, `data`
+/// being loaded at `base`. Stops early at the end of the data.
+pub fn disassemble(
+    data: &[u8],
+    offset: usize,
+    base: u32,
+    count: usize,
+    mode: Mode,
+    endian: Endian,
+) -> Vec<Instruction> {
+    let half = |o: usize| -> Option<u32> {
+        let b = data.get(o..(o + 2))?;
+        Some(match endian {
+            Endian::Little => u16::from_le_bytes([b[0], b[1]]),
+            Endian::Big => u16::from_be_bytes([b[0], b[1]]),
+        } as u32)
+    };
+
+    let mut out = Vec::new();
+    let mut o = offset;
+    while out.len() < count {
+        let address = base.wrapping_add(o as u32);
+        let (d, len, raw) = match mode {
+            Mode::Mips32 => {
+                let w = match data.get(o..(o + 4)) {
+                    Some(b) => endian.read(b),
+                    None => break,
+                };
+                (decode32(w, address), 4, w)
+            }
+            Mode::Mips16 => {
+                let h = match half(o) {
+                    Some(h) => h,
+                    None => break,
+                };
+                let next = half(o + 2);
+                let (d, len) = decode16(h, next, address);
+                let raw = match len {
+                    4 => (h << 16) | next.unwrap_or(0),
+                    _ => h,
+                };
+                (d, len, raw)
+            }
+        };
+        let operands = match d.mnemonic {
+            ".word" if len == 2 => format!("{:#06x}", raw),
+            ".word" => format!("{:#010x}", raw),
+            _ => d.operands,
+        };
+        out.push(Instruction {
+            address,
+            offset: o,
+            len,
+            raw,
+            mnemonic: d.mnemonic,
+            operands,
+            flow: d.flow,
+            target: d.target,
+        });
+        o += len;
+    }
+    out
+}
+
+/// the targets of the branches and jumps of `listing` that fall inside it
+pub fn labels(listing: &[Instruction]) -> BTreeSet<u32> {
+    let inside: BTreeSet<u32> = listing.iter().map(|i| i.address).collect();
+    listing
+        .iter()
+        .filter_map(|i| i.target)
+        .filter(|t| inside.contains(t))
+        .collect()
+}
+
+/// the targets of the calls of `listing`, the functions it uses
+pub fn calls(listing: &[Instruction]) -> BTreeSet<u32> {
+    listing
+        .iter()
+        .filter(|i| i.flow == Flow::Call)
+        .filter_map(|i| i.target)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dis32(words: &[u32], base: u32) -> Vec<Instruction> {
+        let data: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        disassemble(&data, 0, base, words.len(), Mode::Mips32, Endian::Little)
+    }
+
+    fn text(listing: &[Instruction]) -> Vec<String> {
+        listing
+            .iter()
+            .map(|i| format!("{} {}", i.mnemonic, i.operands).trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn mips32() {
+        let listing = dis32(
+            &[
+                0x27bd_ffe8, // addiu sp, sp, -24
+                0xafbf_0014, // sw ra, 20(sp)
+                0x3c04_9d00, // lui a0, 0x9d00
+                0x0f40_0010, // jal 0x9d000040
+                0x2484_1234, // addiu a0, a0, 4660
+                0x1040_fffb, // beqz v0, back to the start
+                0x0000_0000, // nop
+                0x8fbf_0014, // lw ra, 20(sp)
+                0x03e0_0008, // jr ra
+                0x27bd_0018, // addiu sp, sp, 24
+                0x0085_1021, // addu v0, a0, a1
+                0x7c82_1804, // ins v0, a0, 0, 4
+                0xffff_ffff,
+            ],
+            0x9d00_0000,
+        );
+        assert_eq!(
+            text(&listing),
+            [
+                "addiu sp, sp, -24",
+                "sw ra, 20(sp)",
+                "lui a0, 0x9d00",
+                "jal 0x9d000040",
+                "addiu a0, a0, 4660",
+                "beqz v0, 0x9d000004",
+                "nop",
+                "lw ra, 20(sp)",
+                "jr ra",
+                "addiu sp, sp, 24",
+                "addu v0, a0, a1",
+                "ins v0, a0, 0, 4",
+                ".word 0xffffffff",
+            ]
+        );
+        assert_eq!(listing[3].flow, Flow::Call);
+        assert_eq!(listing[5].flow, Flow::Branch);
+        assert_eq!(listing[8].flow, Flow::Return);
+        assert_eq!(
+            labels(&listing).into_iter().collect::<Vec<_>>(),
+            [0x9d00_0004]
+        );
+        assert_eq!(
+            calls(&listing).into_iter().collect::<Vec<_>>(),
+            [0x9d00_0040]
+        );
+    }
+
+    #[test]
+    fn big_endian() {
+        let data = [0x03, 0xe0, 0x00, 0x08];
+        let l = disassemble(&data, 0, 0, 4, Mode::Mips32, Endian::Big);
+        assert_eq!(text(&l), ["jr ra"]);
+    }
+
+    #[test]
+    fn mips16() {
+        let halves: [u16; 10] = [
+            0x64e3, // save ra, s0, 24
+            0x6c05, // li a0, 5
+            0x181a, 0x0010, // jal 0x9d000040
+            0xe489, // addu v0, a0, a0
+            0x2cfb, // bnez a0, back to the li
+            0xf010, 0x6a01, // extended li v0, 0x8001
+            0xe820, // jr ra
+            0x6463, // restore ra, s0, 24
+        ];
+        let data: Vec<u8> = halves.iter().flat_map(|h| h.to_le_bytes()).collect();
+        let l = disassemble(&data, 0, 0x9d00_0000, 20, Mode::Mips16, Endian::Little);
+        assert_eq!(
+            text(&l),
+            [
+                "save ra, s0, 24",
+                "li a0, 5",
+                "jal 0x9d000040",
+                "addu v0, a0, a0",
+                "bnez a0, 0x9d000002",
+                "li v0, 32769",
+                "jr ra",
+                "restore ra, s0, 24",
+            ]
+        );
+        assert_eq!(l[2].len, 4);
+        assert_eq!(l[5].len, 4);
+        assert_eq!(l[6].flow, Flow::Return);
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(disassemble(&[0, 0, 0], 0, 0, 4, Mode::Mips32, Endian::Little).is_empty());
+        let l = disassemble(&[0x03, 0x18], 0, 0, 4, Mode::Mips16, Endian::Little);
+        assert_eq!(l[0].mnemonic, ".word");
+    }
+
+    #[test]
+    fn ins_reversed() {
+        // ins with lsb 4 above msb 0
+        let l = disassemble(
+            &[0x44, 0x01, 0x00, 0x7c],
+            0,
+            0,
+            1,
+            Mode::Mips32,
+            Endian::Little,
+        );
+        assert_eq!(l[0].mnemonic, ".word");
+    }
+}
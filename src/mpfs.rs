@@ -1,22 +1,35 @@
+use crate::error::{Error, Result};
 use crate::maps;
-use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use crate::output::{self, Row};
+use byteorder::{BigEndian, ReadBytesExt};
 use chrono::NaiveDateTime;
+use serde::Serialize;
 use std::fmt;
 
+#[derive(Serialize)]
 pub struct Version {
     major: u8,
     minor: u8,
 }
 
+#[derive(Serialize)]
 pub struct Header {
+    #[serde(serialize_with = "output::lossy")]
     sign: [u8; 4],
     pub ver: Version,
     pub entries: u16,
 }
 
+pub static MAGIC: &[u8; 4] = b"MPFS";
+
 impl Header {
-    pub fn new(reader: &mut maps::ReadableMmap) -> std::io::Result<Self> {
-        Ok(Header {
+    pub const SIZE: usize = 8;
+
+    pub fn new<T: AsRef<[u8]>>(reader: &mut maps::ReadableMmap<T>) -> Result<Self> {
+        let offset = reader.position();
+        reader.require(Self::SIZE)?;
+
+        let header = Header {
             sign: [
                 reader.read_u8()?,
                 reader.read_u8()?,
@@ -28,23 +41,36 @@ impl Header {
                 minor: reader.read_u8()?,
             },
             entries: reader.read_u16::<BigEndian>()?,
-        })
+        };
+
+        if &header.sign != MAGIC {
+            return Err(Error::BadMagic {
+                offset,
+                found: header.sign.to_vec(),
+            });
+        }
+        Ok(header)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
     }
 }
 
 impl fmt::Debug for Header {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(" Header\n")?;
-        f.write_str(&format!(
-            " version: {}.{}\n",
-            self.ver.major, self.ver.minor
-        ))?;
+        f.write_str(&format!(" version: {}\n", self.ver))?;
         f.write_str(&format!(" entries: {}\n", self.entries))?;
         std::result::Result::Ok(())
     }
 }
 
+#[derive(Serialize)]
 pub struct FileHeader {
+    #[serde(serialize_with = "output::lossy")]
     filename: [u8; 4],
     start: u32,
     size: u32,
@@ -54,7 +80,11 @@ pub struct FileHeader {
 }
 
 impl FileHeader {
-    pub fn new(reader: &mut maps::ReadableMmap) -> std::io::Result<Self> {
+    pub const SIZE: usize = 22;
+
+    pub fn new<T: AsRef<[u8]>>(reader: &mut maps::ReadableMmap<T>) -> Result<Self> {
+        reader.require(Self::SIZE)?;
+
         let buf = [
             reader.read_u8()?,
             reader.read_u8()?,
@@ -71,15 +101,87 @@ impl FileHeader {
             flags: reader.read_u16::<BigEndian>()?,
         })
     }
+
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.filename).to_string()
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    /// this file's content, `image` being the whole MPFS image
+    pub fn data<'a>(&self, image: &'a [u8]) -> Result<&'a [u8]> {
+        let start = self.start as usize;
+        let end = start + self.size as usize;
+        if end > image.len() {
+            return Err(Error::InvalidOffset {
+                offset: end,
+                len: image.len(),
+            });
+        }
+        Ok(&image[start..end])
+    }
+}
+
+/// Reads the file table that follows `header`, skipping the name hashes.
+pub fn read_table<T: AsRef<[u8]>>(
+    reader: &mut maps::ReadableMmap<T>,
+    header: &Header,
+) -> Result<Vec<FileHeader>> {
+    reader.seek(header.entries as isize * 2)?;
+    (0..header.entries)
+        .map(|_| FileHeader::new(reader))
+        .collect()
+}
+
+/// Parses the header and file table of the MPFS image at the start of `image`.
+pub fn parse(image: &[u8]) -> Result<(Header, Vec<FileHeader>)> {
+    let mut reader = maps::ReadableMmap::new(image);
+    let header = Header::new(&mut reader)?;
+    let files = read_table(&mut reader, &header)?;
+    Ok((header, files))
+}
+
+/// Where the first MPFS magic is in `data`, if any.
+pub fn find(data: &[u8]) -> Option<usize> {
+    data.windows(MAGIC.len()).position(|w| w == MAGIC)
+}
+
+impl Row for FileHeader {
+    const HEADER: &'static [&'static str] = &[
+        "filename",
+        "start",
+        "size",
+        "timestamp",
+        "microtime",
+        "flags",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name(),
+            self.start.to_string(),
+            self.size.to_string(),
+            self.timestamp.to_string(),
+            self.microtime.to_string(),
+            self.flags.to_string(),
+        ]
+    }
 }
 
 impl fmt::Debug for FileHeader {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str("MPFS File Header\n")?;
-        f.write_str(&format!(
-            " filename: {}\n",
-            std::str::from_utf8(&self.filename).unwrap()
-        ))?;
+        f.write_str(&format!(" filename: {}\n", self.name()))?;
         f.write_str(&format!(
             " start: {:#02x}\tsize: {}\n",
             self.start, self.size
@@ -92,3 +194,90 @@ impl fmt::Debug for FileHeader {
         std::result::Result::Ok(())
     }
 }
+
+/// An MPFS2 image holding `files`, their names being 4 bytes as `name`
+/// returns them, for the tests.
+#[cfg(test)]
+pub(crate) fn image(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut v = MAGIC.to_vec();
+    v.extend_from_slice(&[2, 1]);
+    v.extend_from_slice(&(files.len() as u16).to_be_bytes());
+    // the name hashes
+    v.extend(vec![0; files.len() * 2]);
+    let mut start = v.len() + files.len() * FileHeader::SIZE;
+    for (name, data) in files {
+        assert_eq!(name.len(), 4);
+        v.extend(name.bytes().rev());
+        v.extend_from_slice(&(start as u32).to_be_bytes());
+        v.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        v.extend_from_slice(&[0; 10]);
+        start += data.len();
+    }
+    for (_, data) in files {
+        v.extend_from_slice(data);
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(data: &[u8]) -> maps::ReadableMmap<&[u8]> {
+        maps::ReadableMmap::new(data)
+    }
+
+    #[test]
+    fn header() {
+        let h = Header::new(&mut reader(b"MPFS\x02\x01\x00\x03")).unwrap();
+        assert_eq!(h.ver.major, 2);
+        assert_eq!(h.ver.minor, 1);
+        assert_eq!(h.entries, 3);
+    }
+
+    #[test]
+    fn header_bad_magic() {
+        match Header::new(&mut reader(b"MPFX\x02\x01\x00\x03")) {
+            Err(Error::BadMagic { offset: 0, found }) => assert_eq!(found, b"MPFX"),
+            r => panic!("unexpected {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn header_truncated() {
+        match Header::new(&mut reader(b"MPFS\x02")) {
+            Err(Error::Truncated {
+                offset: 0,
+                needed: 3,
+            }) => (),
+            r => panic!("unexpected {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn file_header_truncated() {
+        let mut r = reader(&[0; 30]);
+        r.seek(10).unwrap();
+        match FileHeader::new(&mut r) {
+            Err(Error::Truncated {
+                offset: 10,
+                needed: 2,
+            }) => (),
+            r => panic!("unexpected {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn table() {
+        let mut image = b"xx".to_vec();
+        image.extend(super::image(&[("abcd", b"hello")]));
+
+        let start = find(&image).unwrap();
+        assert_eq!(start, 2);
+        let image = &image[start..];
+        let (header, files) = parse(image).unwrap();
+        assert_eq!(header.entries, 1);
+        assert_eq!(files[0].name(), "abcd");
+        assert_eq!(files[0].data(image).unwrap(), b"hello");
+    }
+}
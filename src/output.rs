@@ -0,0 +1,154 @@
+use crate::error::Result;
+use serde::{Serialize, Serializer};
+use std::io::{self, Write};
+
+/// Bumped whenever a field is renamed or removed from one of the reports,
+/// adding fields doesn't change it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl Format {
+    /// pulls `--json` and `--csv` out of the arguments, last one wins
+    pub fn from_args(args: &mut Vec<String>) -> Self {
+        let mut format = Format::Text;
+        args.retain(|a| match a.as_str() {
+            "--json" => {
+                format = Format::Json;
+                false
+            }
+            "--csv" => {
+                format = Format::Csv;
+                false
+            }
+            _ => true,
+        });
+        format
+    }
+}
+
+/// What every tool prints in JSON mode, one object per input file and per
+/// line, so the output can be streamed into other tools.
+#[derive(Serialize)]
+pub struct Report<'a, T: Serialize> {
+    pub schema: &'static str,
+    pub version: u32,
+    pub file: &'a str,
+    pub result: T,
+}
+
+pub fn print_json<T: Serialize>(schema: &'static str, file: &str, result: T) -> Result<()> {
+    let report = Report {
+        schema,
+        version: SCHEMA_VERSION,
+        file,
+        result,
+    };
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    serde_json::to_writer(&mut out, &report).map_err(io::Error::from)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Something that can be printed as a line of CSV.
+pub trait Row {
+    const HEADER: &'static [&'static str];
+    fn row(&self) -> Vec<String>;
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// prints `rows` prefixed by a `file` column, the header is only printed
+/// when `header` is set so several files can share one table
+pub fn print_csv<R: Row>(file: &str, rows: &[R], header: bool) -> Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if header {
+        writeln!(out, "file,{}", R::HEADER.join(","))?;
+    }
+    for r in rows {
+        let fields: Vec<String> = r.row().iter().map(|f| csv_field(f)).collect();
+        writeln!(out, "{},{}", csv_field(file), fields.join(","))?;
+    }
+    Ok(())
+}
+
+/// parses an address or an offset given on the command line, `0x` for hex
+pub fn number(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+pub fn to_hex(a: &[u8]) -> String {
+    a.iter().map(|c| format!("{:02x}", c)).collect::<String>()
+}
+
+/// parses hex digits back into bytes, spaces between them are fine
+pub fn from_hex(s: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = s.bytes().filter(|c| *c != b' ').collect();
+    if !digits.len().is_multiple_of(2) || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|d| u8::from_str_radix(std::str::from_utf8(d).ok()?, 16).ok())
+        .collect()
+}
+
+/// serializes bytes as a lowercase hex string
+pub fn hex<S: Serializer>(a: &[u8], s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_str(&to_hex(a))
+}
+
+/// serializes bytes as a string, replacing anything that isn't utf-8
+pub fn lossy<S: Serializer>(a: &[u8], s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_str(&String::from_utf8_lossy(a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_from_args() {
+        let mut args = vec!["a".to_string(), "--json".to_string(), "b".to_string()];
+        assert_eq!(Format::from_args(&mut args), Format::Json);
+        assert_eq!(args, ["a", "b"]);
+
+        let mut args = vec!["--json".to_string(), "--csv".to_string()];
+        assert_eq!(Format::from_args(&mut args), Format::Csv);
+        assert!(args.is_empty());
+
+        let mut args = vec!["a".to_string()];
+        assert_eq!(Format::from_args(&mut args), Format::Text);
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(from_hex("00ff 1a"), Some(vec![0x00, 0xff, 0x1a]));
+        assert_eq!(to_hex(&from_hex("deadbeef").unwrap()), "deadbeef");
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,321 @@
+use crate::cut;
+use crate::error::{Error, Result};
+use crate::output::{self, Row};
+use crate::pattern::Pattern;
+use crate::scan;
+use serde::Serialize;
+
+/// Where a patch goes, inside its member or the whole file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Anchor {
+    At(usize),
+    /// `skip` bytes after the only place `pattern` is found, the pattern
+    /// should be bytes the patch leaves alone or undoing it won't find it
+    Find {
+        #[serde(serialize_with = "output::hex")]
+        pattern: Vec<u8>,
+        skip: usize,
+    },
+}
+
+/// One change, written as
+/// `[member=path] at=offset|find=hex [at=skip] expect=hex replace=hex`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Patch {
+    pub member: Option<String>,
+    pub anchor: Anchor,
+    #[serde(serialize_with = "output::hex")]
+    pub expect: Vec<u8>,
+    #[serde(serialize_with = "output::hex")]
+    pub replace: Vec<u8>,
+}
+
+impl Patch {
+    /// parses one patch, `offset` being where it starts in its file
+    pub fn parse(s: &str, offset: usize) -> Result<Patch> {
+        let malformed = |reason| Error::Malformed { offset, reason };
+        let hex = |v: &str| output::from_hex(v).ok_or_else(|| malformed("bad hex"));
+        let (mut at, mut find, mut expect, mut replace, mut member) =
+            (None, None, None, None, None);
+        for item in s.split_whitespace() {
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| malformed("expected key=value"))?;
+            match key {
+                "member" => member = Some(value.to_string()),
+                "at" => {
+                    at =
+                        Some(output::number(value).ok_or_else(|| malformed("bad offset"))? as usize)
+                }
+                "find" => find = Some(hex(value)?),
+                "expect" => expect = Some(hex(value)?),
+                "replace" => replace = Some(hex(value)?),
+                _ => return Err(malformed("unknown key")),
+            }
+        }
+        let anchor = match (find, at) {
+            (Some(pattern), skip) if !pattern.is_empty() => Anchor::Find {
+                pattern,
+                skip: skip.unwrap_or(0),
+            },
+            (None, Some(at)) => Anchor::At(at),
+            _ => return Err(malformed("missing at or find")),
+        };
+        let expect = expect.ok_or_else(|| malformed("missing expect"))?;
+        let replace = replace.ok_or_else(|| malformed("missing replace"))?;
+        if expect.is_empty() {
+            return Err(malformed("nothing to patch"));
+        }
+        if expect.len() != replace.len() {
+            return Err(malformed("expect and replace have different lengths"));
+        }
+        Ok(Patch {
+            member,
+            anchor,
+            expect,
+            replace,
+        })
+    }
+
+    /// one patch per line, `#` starts a comment
+    pub fn parse_all(text: &str) -> Result<Vec<Patch>> {
+        let mut patches = Vec::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let content = line.split('#').next().unwrap_or("");
+            if !content.trim().is_empty() {
+                patches.push(Patch::parse(content, offset)?);
+            }
+            offset += line.len();
+        }
+        Ok(patches)
+    }
+
+    /// the offset in `data` the patch applies to
+    pub fn locate(&self, data: &[u8]) -> Result<usize> {
+        let (first, last) = match &self.member {
+            None => (0, data.len()),
+            Some(path) => cut::split(data)?
+                .iter()
+                .find(|m| &m.path == path)
+                .map(|m| (m.offset, m.offset + m.len))
+                .ok_or(Error::Malformed {
+                    offset: 0,
+                    reason: "no such member",
+                })?,
+        };
+        let offset = match &self.anchor {
+            Anchor::At(at) => *at,
+            Anchor::Find { pattern, skip } => {
+                let matches = scan::scan(&data[first..last], &mut [Pattern::new(pattern)]);
+                match matches.as_slice() {
+                    [m] => m.offset + skip,
+                    [] => {
+                        return Err(Error::Malformed {
+                            offset: first,
+                            reason: "anchor not found",
+                        })
+                    }
+                    [_, m, ..] => {
+                        return Err(Error::Malformed {
+                            offset: first + m.offset,
+                            reason: "anchor found more than once",
+                        })
+                    }
+                }
+            }
+        };
+        if offset + self.expect.len() > last - first {
+            return Err(Error::InvalidOffset {
+                offset: first + offset,
+                len: last,
+            });
+        }
+        Ok(first + offset)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// the original bytes are there, the patch can go in
+    Ready,
+    /// the replacement is already there
+    Done,
+    /// neither, somebody else changed those bytes
+    Mismatch,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Ready => "ready",
+            Status::Done => "done",
+            Status::Mismatch => "mismatch",
+        }
+    }
+}
+
+/// What a patch finds in an image.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Outcome {
+    /// index of the patch in the patch file
+    pub patch: usize,
+    pub offset: usize,
+    pub status: Status,
+    #[serde(serialize_with = "output::hex")]
+    pub found: Vec<u8>,
+}
+
+impl Row for Outcome {
+    const HEADER: &'static [&'static str] = &["patch", "offset", "status", "found"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.patch.to_string(),
+            self.offset.to_string(),
+            self.status.name().to_string(),
+            output::to_hex(&self.found),
+        ]
+    }
+}
+
+/// What applying (or with `revert`, undoing) `patches` would do to `data`,
+/// they're all located before anything is written. Patches touching the
+/// same bytes are refused, the second would undo part of the first and
+/// reverting couldn't bring the original back.
+pub fn plan(data: &[u8], patches: &[Patch], revert: bool) -> Result<Vec<Outcome>> {
+    let outcomes: Vec<Outcome> = patches
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let offset = p.locate(data)?;
+            let found = &data[offset..(offset + p.expect.len())];
+            let (from, to) = match revert {
+                false => (&p.expect, &p.replace),
+                true => (&p.replace, &p.expect),
+            };
+            let status = if found == to.as_slice() {
+                Status::Done
+            } else if found == from.as_slice() {
+                Status::Ready
+            } else {
+                Status::Mismatch
+            };
+            Ok(Outcome {
+                patch: i,
+                offset,
+                status,
+                found: found.to_vec(),
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let mut ranges: Vec<(usize, usize)> = outcomes
+        .iter()
+        .map(|o| (o.offset, o.offset + o.found.len()))
+        .collect();
+    ranges.sort_unstable();
+    for w in ranges.windows(2) {
+        if w[1].0 < w[0].1 {
+            return Err(Error::Malformed {
+                offset: w[1].0,
+                reason: "patches overlap",
+            });
+        }
+    }
+    Ok(outcomes)
+}
+
+/// Applies (or undoes) `patches`, all or nothing: when one of them finds
+/// something unexpected `data` is left alone.
+pub fn apply(data: &mut [u8], patches: &[Patch], revert: bool) -> Result<Vec<Outcome>> {
+    let outcomes = plan(data, patches, revert)?;
+    if outcomes.iter().any(|o| o.status == Status::Mismatch) {
+        return Ok(outcomes);
+    }
+    for (o, p) in outcomes.iter().zip(patches) {
+        if o.status == Status::Ready {
+            let to = if revert { &p.expect } else { &p.replace };
+            data[o.offset..(o.offset + to.len())].copy_from_slice(to);
+        }
+    }
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCHES: &str = "# nop a call\n\
+        at=4 expect=0c000010 replace=00000000\n\
+        find=cafe at=2 expect=0102 replace=0304\n";
+
+    fn image() -> Vec<u8> {
+        let mut data = vec![0xaa; 16];
+        data[4..8].copy_from_slice(&[0x0c, 0x00, 0x00, 0x10]);
+        data[10..14].copy_from_slice(&[0xca, 0xfe, 0x01, 0x02]);
+        data
+    }
+
+    #[test]
+    fn parse() {
+        let p = Patch::parse_all(PATCHES).unwrap();
+        assert_eq!(p.len(), 2);
+        assert_eq!(
+            p[1].anchor,
+            Anchor::Find {
+                pattern: vec![0xca, 0xfe],
+                skip: 2
+            }
+        );
+        assert!(Patch::parse("at=0 expect=00 replace=0000", 0).is_err());
+        assert!(Patch::parse("expect=00 replace=01", 0).is_err());
+    }
+
+    #[test]
+    fn apply_and_revert() {
+        let patches = Patch::parse_all(PATCHES).unwrap();
+        let original = image();
+        let mut data = original.clone();
+        let o = apply(&mut data, &patches, false).unwrap();
+        assert!(o.iter().all(|o| o.status == Status::Ready));
+        assert_eq!(o[1].offset, 12);
+        assert_eq!(&data[4..8], [0; 4]);
+        assert_eq!(&data[12..14], [3, 4]);
+
+        let o = plan(&data, &patches, false).unwrap();
+        assert!(o.iter().all(|o| o.status == Status::Done));
+
+        apply(&mut data, &patches, true).unwrap();
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn mismatch() {
+        let patches = Patch::parse_all(PATCHES).unwrap();
+        let mut data = image();
+        data[13] = 0xff;
+        let o = apply(&mut data, &patches, false).unwrap();
+        assert_eq!(o[1].status, Status::Mismatch);
+        // the first patch didn't go in either
+        assert_eq!(data[4..8], [0x0c, 0, 0, 0x10]);
+    }
+
+    #[test]
+    fn overlap() {
+        let mut patches = Patch::parse_all(PATCHES).unwrap();
+        patches.push(Patch::parse("at=6 expect=0010aa replace=000000", 0).unwrap());
+        let mut data = image();
+        assert!(matches!(
+            apply(&mut data, &patches, false),
+            Err(Error::Malformed { offset: 6, .. })
+        ));
+        assert_eq!(data, image());
+        // touching is fine
+        patches[2] = Patch::parse("at=8 expect=aaaa replace=0000", 0).unwrap();
+        apply(&mut data, &patches, false).unwrap();
+        assert_eq!(&data[4..10], [0; 6]);
+    }
+}
@@ -1,12 +1,14 @@
 use crate::circular_buffer::CircularBuffer;
 use std::fmt;
 
+#[derive(Clone, PartialEq)]
 struct Indexes {
     i: usize,
     match_count: usize,
     min_match: usize,
 }
 
+#[derive(Clone, PartialEq)]
 pub struct Pattern<'a> {
     pattern: &'a [u8],
     lookback: CircularBuffer,
@@ -38,7 +40,7 @@ impl<'a> Pattern<'a> {
 
         //        println!(">>> {} ({:#?})", c as char, self.lookback);
         // no match, reset and return
-        if c != self.pattern[*i] as u8 {
+        if c != self.pattern[*i] {
             if looking_back {
                 for _ in 0..self.idx.match_count {
                     for p in self.pattern.iter() {
@@ -53,7 +55,7 @@ impl<'a> Pattern<'a> {
 
             self.idx.match_count = 0;
 
-            if c != self.pattern[0] as u8 {
+            if c != self.pattern[0] {
                 *i = 0;
                 self.lookback.push(c);
                 return None;
@@ -84,10 +86,51 @@ impl<'a> Pattern<'a> {
         Some((l * (self.idx.match_count)) as isize)
     }
 
+    /// Pushes all of `data`, calling `f` with the index of the byte that
+    /// completed every match and what `push` returned for it. While nothing
+    /// is being matched it jumps to the next first byte of the pattern
+    /// instead of pushing the bytes in between, which only fill the
+    /// lookback, so the results are those of `push`.
+    pub fn feed<F: FnMut(usize, isize, &Self)>(&mut self, data: &[u8], mut f: F) {
+        if self.pattern.is_empty() {
+            return;
+        }
+        let mut at = 0;
+        while at < data.len() {
+            if self.idx.i == 0 && self.idx.match_count == 0 {
+                let next =
+                    memchr::memchr(self.pattern[0], &data[at..]).map_or(data.len(), |n| at + n);
+                let from = next.saturating_sub(self.lookback.len()).max(at);
+                for c in data[from..next].iter() {
+                    self.lookback.push(*c);
+                }
+                at = next;
+                if at == data.len() {
+                    break;
+                }
+            }
+            if let Some(n) = self.push(data[at]) {
+                f(at, n, self);
+            }
+            at += 1;
+        }
+    }
+
+    pub fn pattern(&self) -> &'a [u8] {
+        self.pattern
+    }
+
+    /// how many bytes back a match can start, lookback included
+    pub fn reach(&self) -> usize {
+        self.pattern.len() + self.lookback.len()
+    }
+
+    /// the lookback as it is, in the middle of a match it's missing the part
+    /// of the pattern seen so far, which `get` adds
     pub fn _ro_get(&'a self) -> &'a [u8] {
         let buf = &self.lookback;
-        if !(0..(self.idx.i)).is_empty() {
-            println!("WE ARE MISSING DATA ! you should use .get()");
+        if buf.is_empty() {
+            return &[];
         }
         &buf[0..buf.len()]
     }
@@ -162,7 +205,7 @@ mod tests {
         let mut p = Pattern::new(b"a");
         assert_eq!(p.push(b'0'), None);
         assert_eq!(p.push(b'a'), Some(1));
-        assert_eq!(p.get(), []);
+        assert_eq!(p.get(), b"");
     }
 
     #[test]
@@ -171,7 +214,7 @@ mod tests {
         assert_eq!(p.push(0), None);
         assert_eq!(p.push(b'a'), None);
         assert_eq!(p.push(b'b'), Some(1));
-        assert_eq!(p.get(), []);
+        assert_eq!(p.get(), b"");
     }
 
     #[test]
@@ -180,7 +223,7 @@ mod tests {
         assert_eq!(p.push(b'0'), None);
         assert_eq!(p.push(b'a'), Some(1));
         assert_eq!(p.push(b'a'), Some(1));
-        assert_eq!(p.get(), []);
+        assert_eq!(p.get(), b"");
     }
 
     #[test]
@@ -191,7 +234,7 @@ mod tests {
         assert_eq!(p.push(b'b'), Some(2));
         assert_eq!(p.push(b'a'), None);
         assert_eq!(p.push(b'b'), Some(2));
-        assert_eq!(p.get(), []);
+        assert_eq!(p.get(), b"");
     }
 
     #[test]
@@ -201,7 +244,7 @@ mod tests {
         assert_eq!(p.push(b'a'), None);
         assert_eq!(p.push(b'b'), None);
         assert_eq!(p.push(b'c'), Some(2));
-        assert_eq!(p.get(), []);
+        assert_eq!(p.get(), b"");
     }
 
     #[test]
@@ -213,7 +256,43 @@ mod tests {
         assert_eq!(p.push(b'c'), None);
         assert_eq!(p.push(b'd'), None);
         assert_eq!(p.push(b'e'), Some(3));
-        assert_eq!(p.get(), []);
+        assert_eq!(p.get(), b"");
+    }
+
+    #[test]
+    fn feed() {
+        let mut x: u32 = 7;
+        let data: Vec<u8> = (0..20000)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345);
+                b"\0\0abcd"[(x >> 16) as usize % 6]
+            })
+            .collect();
+        for p in [
+            Pattern::new(b"\0").lookback(20).repeats(4),
+            Pattern::new(b"cd").repeats(2).lookback(2),
+            Pattern::new(b"ab").lookback(3),
+            Pattern::new(b"aab"),
+            Pattern::new(b"d").lookback(1),
+        ] {
+            let mut q = p.clone();
+            let pushed: Vec<(usize, isize, Vec<u8>)> = data
+                .iter()
+                .enumerate()
+                .filter_map(|(i, c)| q.push(*c).map(|n| (i, n, q._ro_get().to_vec())))
+                .collect();
+            let mut fed = Vec::new();
+            let mut r = p.clone();
+            // in two goes, a match can span both
+            for (start, half) in [(0, &data[..10001]), (10001, &data[10001..])] {
+                r.feed(half, |i, n, r| {
+                    fed.push((start + i, n, r._ro_get().to_vec()))
+                });
+            }
+            assert!(!pushed.is_empty());
+            assert_eq!(fed, pushed, "{:?}", p);
+            assert!(r == q);
+        }
     }
 
     #[test]
@@ -261,7 +340,7 @@ mod tests {
         assert_eq!(p.push(b'0'), None);
         assert_eq!(p.push(b'a'), None);
         assert_eq!(p.push(b'a'), Some(2));
-        assert_eq!(p.get(), []);
+        assert_eq!(p.get(), b"");
     }
 
     #[test]
@@ -271,7 +350,7 @@ mod tests {
         assert_eq!(p.push(b'a'), None);
         assert_eq!(p.push(b'b'), None);
         assert_eq!(p.push(b'b'), Some(2));
-        assert_eq!(p.get(), []);
+        assert_eq!(p.get(), b"");
     }
 
     #[test]
@@ -283,7 +362,7 @@ mod tests {
         assert_eq!(p.push(b'c'), None);
         assert_eq!(p.push(b'b'), None);
         assert_eq!(p.push(b'c'), Some(4));
-        assert_eq!(p.get(), []);
+        assert_eq!(p.get(), b"");
     }
 
     #[test]
@@ -0,0 +1,439 @@
+use crate::ihex;
+use crate::output::Row;
+use serde::Serialize;
+
+/// The PIC32MX memory map. MZ parts move things around, and the sizes are
+/// the biggest of the family (the cube runs a PIC32MX795).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Space {
+    /// 0x80000000-0x9fffffff, cached
+    Kseg0,
+    /// 0xa0000000-0xbfffffff, uncached
+    Kseg1,
+    /// what the bus sees, and what the HEX files are written in
+    Physical,
+}
+
+impl Space {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Space::Kseg0 => "kseg0",
+            Space::Kseg1 => "kseg1",
+            Space::Physical => "physical",
+        }
+    }
+
+    pub fn of(address: u32) -> Self {
+        match address {
+            0x8000_0000..=0x9fff_ffff => Space::Kseg0,
+            0xa000_0000..=0xbfff_ffff => Space::Kseg1,
+            _ => Space::Physical,
+        }
+    }
+}
+
+pub fn physical(address: u32) -> u32 {
+    match Space::of(address) {
+        Space::Kseg0 | Space::Kseg1 => address & 0x1fff_ffff,
+        Space::Physical => address,
+    }
+}
+
+pub fn kseg0(address: u32) -> u32 {
+    physical(address) | 0x8000_0000
+}
+
+pub fn kseg1(address: u32) -> u32 {
+    physical(address) | 0xa000_0000
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Region {
+    pub name: &'static str,
+    /// physical address
+    pub start: u32,
+    pub len: u32,
+}
+
+impl Region {
+    pub fn contains(&self, address: u32) -> bool {
+        let p = physical(address);
+        p >= self.start && p - self.start < self.len
+    }
+}
+
+pub static REGIONS: &[Region] = &[
+    Region {
+        name: "ram",
+        start: 0x0000_0000,
+        len: 0x2_0000,
+    },
+    Region {
+        name: "program flash",
+        start: 0x1d00_0000,
+        len: 0x8_0000,
+    },
+    Region {
+        name: "sfr",
+        start: 0x1f80_0000,
+        len: 0x10_0000,
+    },
+    Region {
+        name: "boot flash",
+        start: 0x1fc0_0000,
+        len: 0x3000,
+    },
+];
+
+/// the region holding `address`, in any of the three spaces
+pub fn region(address: u32) -> Option<&'static Region> {
+    REGIONS.iter().find(|r| r.contains(address))
+}
+
+/// the configuration words sit at the end of the boot flash, DEVCFG3 first
+pub const DEVCFG3: u32 = 0x1fc0_2ff0;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Field {
+    pub word: &'static str,
+    pub name: &'static str,
+    pub value: u32,
+    pub meaning: String,
+}
+
+impl Row for Field {
+    const HEADER: &'static [&'static str] = &["word", "field", "value", "meaning"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.word.to_string(),
+            self.name.to_string(),
+            self.value.to_string(),
+            self.meaning.clone(),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConfigWord {
+    pub name: &'static str,
+    /// physical address
+    pub address: u32,
+    pub value: u32,
+    pub fields: Vec<Field>,
+}
+
+fn bits(value: u32, high: u32, low: u32) -> u32 {
+    (value >> low) & ((1 << (high - low + 1)) - 1)
+}
+
+/// for the many bits where 0 turns something on
+fn enabled_low(v: u32) -> String {
+    match v {
+        0 => "enabled",
+        _ => "disabled",
+    }
+    .to_string()
+}
+
+fn enabled_high(v: u32) -> String {
+    match v {
+        0 => "disabled",
+        _ => "enabled",
+    }
+    .to_string()
+}
+
+fn pin(v: u32, name: &str) -> String {
+    match v {
+        0 => format!("{} pin controlled by the port", name),
+        _ => format!("{} pin controlled by its module", name),
+    }
+}
+
+/// name, high and low bits, and what the value means
+type FieldDef = (&'static str, u32, u32, fn(u32) -> String);
+
+fn devcfg0() -> Vec<FieldDef> {
+    vec![
+        ("CP", 28, 28, |v| format!("code protect {}", enabled_low(v))),
+        ("BWP", 24, 24, |v| match v {
+            0 => "boot flash write protected".to_string(),
+            _ => "boot flash writable".to_string(),
+        }),
+        ("PWP", 19, 12, |v| match 0xff - v {
+            0 => "program flash not write protected".to_string(),
+            n => format!("first {}k of program flash write protected", n * 4),
+        }),
+        ("ICESEL", 3, 3, |v| match v {
+            0 => "debugger on PGEC1/PGED1".to_string(),
+            _ => "debugger on PGEC2/PGED2".to_string(),
+        }),
+        // 01 and 00 are reserved and behave like 11
+        ("DEBUG", 1, 0, |v| match v {
+            0b10 => "background debugger enabled".to_string(),
+            _ => "background debugger disabled".to_string(),
+        }),
+    ]
+}
+
+fn devcfg1() -> Vec<FieldDef> {
+    vec![
+        ("FWDTEN", 23, 23, |v| {
+            format!("watchdog {}", enabled_high(v))
+        }),
+        ("WDTPS", 20, 16, |v| {
+            format!("watchdog postscaler 1:{}", 1u32 << v.min(20))
+        }),
+        ("FCKSM", 15, 14, |v| {
+            match v {
+                0 => "clock switching and fail-safe monitor enabled",
+                1 => "clock switching enabled, fail-safe monitor disabled",
+                _ => "clock switching and fail-safe monitor disabled",
+            }
+            .to_string()
+        }),
+        ("FPBDIV", 13, 12, |v| {
+            format!("peripheral clock is system clock / {}", 1 << v)
+        }),
+        ("OSCIOFNC", 10, 10, |v| {
+            format!("CLKO output {}", enabled_low(v))
+        }),
+        ("POSCMOD", 9, 8, |v| {
+            match v {
+                0 => "primary oscillator external clock",
+                1 => "primary oscillator XT",
+                2 => "primary oscillator HS",
+                _ => "primary oscillator disabled",
+            }
+            .to_string()
+        }),
+        ("IESO", 7, 7, |v| {
+            format!("two-speed start-up {}", enabled_high(v))
+        }),
+        ("FSOSCEN", 5, 5, |v| {
+            format!("secondary oscillator {}", enabled_high(v))
+        }),
+        ("FNOSC", 2, 0, |v| {
+            [
+                "fast RC",
+                "fast RC with PLL",
+                "primary oscillator",
+                "primary oscillator with PLL",
+                "secondary oscillator",
+                "low power RC",
+                "fast RC / 16",
+                "fast RC / FRCDIV",
+            ][v as usize]
+                .to_string()
+        }),
+    ]
+}
+
+static DIVIDERS: [u32; 8] = [1, 2, 3, 4, 5, 6, 10, 12];
+
+fn devcfg2() -> Vec<FieldDef> {
+    vec![
+        ("FPLLODIV", 18, 16, |v| {
+            format!("PLL output / {}", [1, 2, 4, 8, 16, 32, 64, 256][v as usize])
+        }),
+        ("UPLLEN", 15, 15, |v| format!("USB PLL {}", enabled_low(v))),
+        ("UPLLIDIV", 10, 8, |v| {
+            format!("USB PLL input / {}", DIVIDERS[v as usize])
+        }),
+        ("FPLLMUL", 6, 4, |v| {
+            format!("PLL x {}", [15, 16, 17, 18, 19, 20, 21, 24][v as usize])
+        }),
+        ("FPLLIDIV", 2, 0, |v| {
+            format!("PLL input / {}", DIVIDERS[v as usize])
+        }),
+    ]
+}
+
+fn devcfg3() -> Vec<FieldDef> {
+    vec![
+        ("FVBUSONIO", 31, 31, |v| pin(v, "VBUSON")),
+        ("FUSBIDIO", 30, 30, |v| pin(v, "USBID")),
+        ("FCANIO", 25, 25, |v| match v {
+            0 => "CAN on alternate pins".to_string(),
+            _ => "CAN on default pins".to_string(),
+        }),
+        ("FETHIO", 24, 24, |v| match v {
+            0 => "ethernet on alternate pins".to_string(),
+            _ => "ethernet on default pins".to_string(),
+        }),
+        ("FMIIEN", 22, 22, |v| match v {
+            0 => "ethernet RMII".to_string(),
+            _ => "ethernet MII".to_string(),
+        }),
+        ("FSRSSEL", 18, 16, |v| {
+            format!("shadow registers for priority {}", v)
+        }),
+        ("USERID", 15, 0, |v| format!("{:#06x}", v)),
+    ]
+}
+
+/// Decodes DEVCFG`n` into its fields, unused bits are left out.
+pub fn decode_word(n: u32, value: u32) -> ConfigWord {
+    let (name, fields) = match n {
+        0 => ("DEVCFG0", devcfg0()),
+        1 => ("DEVCFG1", devcfg1()),
+        2 => ("DEVCFG2", devcfg2()),
+        _ => ("DEVCFG3", devcfg3()),
+    };
+    ConfigWord {
+        name,
+        address: DEVCFG3 + 4 * (3 - n.min(3)),
+        value,
+        fields: fields
+            .into_iter()
+            .map(|(field, high, low, decode)| {
+                let v = bits(value, high, low);
+                Field {
+                    word: name,
+                    name: field,
+                    value: v,
+                    meaning: decode(v),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// `len` bytes at physical `address`, the HEX file may use any space
+fn get(image: &ihex::Image, address: u32, len: usize) -> Option<&[u8]> {
+    [address, kseg1(address), kseg0(address)]
+        .iter()
+        .find_map(|a| image.get(*a, len))
+}
+
+/// The DEVCFG0-3 words of `image`, those it doesn't hold are left out.
+pub fn config(image: &ihex::Image) -> Vec<ConfigWord> {
+    (0..4)
+        .filter_map(|n| {
+            let w = get(image, DEVCFG3 + 4 * (3 - n), 4)?;
+            Some(decode_word(n, u32::from_le_bytes([w[0], w[1], w[2], w[3]])))
+        })
+        .collect()
+}
+
+/// Where a segment of a HEX file lands.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Placement {
+    pub address: u32,
+    pub len: usize,
+    pub space: Space,
+    pub physical: u32,
+    pub region: Option<&'static str>,
+}
+
+impl Row for Placement {
+    const HEADER: &'static [&'static str] = &["address", "len", "space", "physical", "region"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            format!("{:#010x}", self.address),
+            self.len.to_string(),
+            self.space.name().to_string(),
+            format!("{:#010x}", self.physical),
+            self.region.unwrap_or("").to_string(),
+        ]
+    }
+}
+
+pub fn placements(image: &ihex::Image) -> Vec<Placement> {
+    image
+        .segments
+        .iter()
+        .map(|s| Placement {
+            address: s.address,
+            len: s.data.len(),
+            space: Space::of(s.address),
+            physical: physical(s.address),
+            region: region(s.address).map(|r| r.name),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translation() {
+        assert_eq!(physical(0x9d00_1000), 0x1d00_1000);
+        assert_eq!(physical(0xbfc0_0000), 0x1fc0_0000);
+        assert_eq!(physical(0x1d00_0000), 0x1d00_0000);
+        assert_eq!(kseg0(0xbd00_0004), 0x9d00_0004);
+        assert_eq!(kseg1(0x1fc0_0000), 0xbfc0_0000);
+        assert_eq!(Space::of(0xa000_0000), Space::Kseg1);
+        assert_eq!(region(0x9d07_ffff).unwrap().name, "program flash");
+        assert_eq!(region(0xbfc0_2ffc).unwrap().name, "boot flash");
+        assert_eq!(region(0xa000_1000).unwrap().name, "ram");
+        assert!(region(0x1d08_0000).is_none());
+    }
+
+    fn field<'a>(w: &'a ConfigWord, name: &str) -> &'a Field {
+        w.fields.iter().find(|f| f.name == name).unwrap()
+    }
+
+    #[test]
+    fn words() {
+        // a typical 80MHz setup: 8MHz HS crystal / 2 x 20 / 1, watchdog off
+        let cfg0 = decode_word(0, 0x7fff_fffe);
+        assert_eq!(field(&cfg0, "CP").meaning, "code protect disabled");
+        assert_eq!(field(&cfg0, "DEBUG").meaning, "background debugger enabled");
+        let cfg0 = decode_word(0, 0x7fff_fffd);
+        assert_eq!(
+            field(&cfg0, "DEBUG").meaning,
+            "background debugger disabled"
+        );
+
+        let cfg1 = decode_word(1, 0xff60_ce5b);
+        assert_eq!(field(&cfg1, "FNOSC").meaning, "primary oscillator with PLL");
+        assert_eq!(field(&cfg1, "POSCMOD").meaning, "primary oscillator HS");
+        assert_eq!(field(&cfg1, "FWDTEN").meaning, "watchdog disabled");
+        assert_eq!(field(&cfg1, "FPBDIV").value, 0);
+
+        let cfg2 = decode_word(2, 0xfff8_79d9);
+        assert_eq!(field(&cfg2, "FPLLIDIV").meaning, "PLL input / 2");
+        assert_eq!(field(&cfg2, "FPLLMUL").meaning, "PLL x 20");
+        assert_eq!(field(&cfg2, "FPLLODIV").meaning, "PLL output / 1");
+        assert_eq!(cfg2.address, 0x1fc0_2ff4);
+    }
+
+    /// an Intel HEX data record
+    fn record(address: u16, data: &[u8]) -> String {
+        let mut bytes = vec![data.len() as u8, (address >> 8) as u8, address as u8, 0];
+        bytes.extend_from_slice(data);
+        let sum = bytes
+            .iter()
+            .fold(0u8, |a, b| a.wrapping_add(*b))
+            .wrapping_neg();
+        bytes.push(sum);
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        format!(":{}\n", hex)
+    }
+
+    #[test]
+    fn from_hex() {
+        let mut text = ":020000041fc01b\n".to_string();
+        text += &record(
+            0x2ff0,
+            &[
+                0xff, 0xff, 0x00, 0x00, 0xd9, 0x79, 0xf8, 0xff, 0x5b, 0xce, 0x60, 0xff, 0xfd, 0xff,
+                0xff, 0x7f,
+            ],
+        );
+        text += ":00000001ff\n";
+
+        let image = ihex::Image::parse(text.as_bytes()).unwrap();
+        let words = config(&image);
+        assert_eq!(words.len(), 4);
+        assert_eq!(words[0].name, "DEVCFG0");
+        assert_eq!(words[0].value, 0x7fff_fffd);
+        assert_eq!(words[3].value, 0x0000_ffff);
+        assert_eq!(placements(&image)[0].region, Some("boot flash"));
+    }
+}
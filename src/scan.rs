@@ -0,0 +1,296 @@
+use crate::output::{self, Row};
+use crate::pattern::Pattern;
+use serde::Serialize;
+use std::io::{self, Read};
+use std::thread;
+
+/// how much `scan_reader` reads at once
+const BUFFER: usize = 64 * 1024;
+/// below this a chunk isn't worth a thread
+const MIN_CHUNK: usize = 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Match {
+    /// index of the pattern that matched in the slice given to `scan`
+    pub pattern: usize,
+    pub offset: usize,
+    /// for repeating patterns this is the whole run
+    pub len: usize,
+    /// the pattern's lookback when it matched
+    #[serde(serialize_with = "output::hex")]
+    pub context: Vec<u8>,
+}
+
+/// Scans input that comes in pieces, a match can span two of them.
+#[derive(Clone, Debug, Default)]
+pub struct Scanner {
+    pub matches: Vec<Match>,
+    /// bytes fed so far
+    offset: usize,
+    /// index in `matches` of the last match of every pattern
+    lasts: Vec<Option<usize>>,
+}
+
+impl Scanner {
+    pub fn new(patterns: usize) -> Self {
+        Scanner {
+            lasts: vec![None; patterns],
+            ..Scanner::default()
+        }
+    }
+
+    /// for a chunk starting at `offset` in the input
+    fn at(patterns: usize, offset: usize) -> Self {
+        Scanner {
+            offset,
+            ..Scanner::new(patterns)
+        }
+    }
+
+    /// appends the matches of the chunk that follows, joining the runs
+    /// that span both
+    fn extend(&mut self, matches: Vec<Match>) {
+        for m in matches {
+            match self.lasts[m.pattern] {
+                Some(k) if self.matches[k].offset == m.offset => self.matches[k].len = m.len,
+                _ => {
+                    self.lasts[m.pattern] = Some(self.matches.len());
+                    self.matches.push(m);
+                }
+            }
+        }
+    }
+
+    /// feeds the next bytes to `patterns`, always the same ones
+    pub fn feed(&mut self, data: &[u8], patterns: &mut [Pattern]) {
+        // every pattern goes through `data` on its own, the new matches are
+        // then put back in the order a byte at a time would find them in
+        let mut found: Vec<(usize, Match)> = Vec::new();
+        let matches = &mut self.matches;
+        let base = self.offset;
+        for (j, p) in patterns.iter_mut().enumerate() {
+            let mut last = self.lasts[j].map(|k| (false, k));
+            p.feed(data, |i, n, p| {
+                let len = n as usize;
+                let offset = base + i + 1 - len;
+                match last {
+                    Some((true, k)) if found[k].1.offset == offset => found[k].1.len = len,
+                    Some((false, k)) if matches[k].offset == offset => matches[k].len = len,
+                    _ => {
+                        last = Some((true, found.len()));
+                        let context = p._ro_get().to_vec();
+                        found.push((
+                            i,
+                            Match {
+                                pattern: j,
+                                offset,
+                                len,
+                                context,
+                            },
+                        ));
+                    }
+                }
+            });
+        }
+        found.sort_by_key(|(i, m)| (*i, m.pattern));
+        for (_, m) in found {
+            self.lasts[m.pattern] = Some(self.matches.len());
+            self.matches.push(m);
+        }
+        self.offset += data.len();
+    }
+}
+
+/// Feeds every byte of `data` to all `patterns`, a repeating pattern yields
+/// one match covering the whole run.
+pub fn scan(data: &[u8], patterns: &mut [Pattern]) -> Vec<Match> {
+    let mut s = Scanner::new(patterns.len());
+    s.feed(data, patterns);
+    s.matches
+}
+
+/// Like `scan`, reading `r` a buffer at a time instead of holding it all.
+pub fn scan_reader<R: Read>(mut r: R, patterns: &mut [Pattern]) -> io::Result<Vec<Match>> {
+    let mut s = Scanner::new(patterns.len());
+    let mut buf = vec![0; BUFFER];
+    loop {
+        match r.read(&mut buf) {
+            Ok(0) => return Ok(s.matches),
+            Ok(n) => s.feed(&buf[..n], patterns),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A chunk scanned on its own thread.
+struct Chunk<'a> {
+    start: usize,
+    end: usize,
+    /// the patterns once warmed up on the bytes before `start`
+    warm: Vec<Pattern<'a>>,
+    matches: Vec<Match>,
+    /// and after `end`
+    last: Vec<Pattern<'a>>,
+}
+
+/// Like `scan` on all the cores, for large images.
+pub fn scan_parallel(data: &[u8], patterns: &mut [Pattern]) -> Vec<Match> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    scan_chunks(data, patterns, (data.len() / threads).max(MIN_CHUNK))
+}
+
+/// Every chunk first feeds the patterns the longest pattern plus lookback
+/// before it. When that leaves them in the state the previous chunk ended
+/// in, what follows is what `scan` would see; when it doesn't, say in the
+/// middle of a run, the chunk is rescanned from that state, so the
+/// matches are always exactly those of `scan`.
+fn scan_chunks(data: &[u8], patterns: &mut [Pattern], chunk: usize) -> Vec<Match> {
+    if data.len() <= chunk {
+        return scan(data, patterns);
+    }
+    let initial: &[Pattern] = patterns;
+    let overlap = initial.iter().map(Pattern::reach).max().unwrap_or(0);
+    let chunks: Vec<Chunk> = thread::scope(|s| {
+        let workers: Vec<_> = (0..data.len())
+            .step_by(chunk)
+            .map(|start| {
+                s.spawn(move || {
+                    let end = (start + chunk).min(data.len());
+                    let mut ps = initial.to_vec();
+                    for c in data[start.saturating_sub(overlap)..start].iter() {
+                        for p in ps.iter_mut() {
+                            p.push(*c);
+                        }
+                    }
+                    let warm = ps.clone();
+                    let mut s = Scanner::at(ps.len(), start);
+                    s.feed(&data[start..end], &mut ps);
+                    Chunk {
+                        start,
+                        end,
+                        warm,
+                        matches: s.matches,
+                        last: ps,
+                    }
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    let mut scanner = Scanner::new(patterns.len());
+    let mut state = patterns.to_vec();
+    for c in chunks {
+        if c.warm == state {
+            scanner.extend(c.matches);
+            state = c.last;
+        } else {
+            let mut s = Scanner::at(state.len(), c.start);
+            s.feed(&data[c.start..c.end], &mut state);
+            scanner.extend(s.matches);
+        }
+    }
+    patterns.clone_from_slice(&state);
+    scanner.matches
+}
+
+impl Match {
+    /// same layout as `Pattern`'s debug output, `pattern` is what matched
+    pub fn describe(&self, pattern: &[u8]) -> String {
+        let hex = |a: &[u8]| a.iter().map(|c| format!("{:02x} ", c)).collect::<String>();
+        let mut s = String::new();
+        if !self.context.is_empty() {
+            s += &format!("[ {}] ", hex(&self.context));
+        }
+        if pattern.is_empty() || self.len == pattern.len() {
+            s += &hex(pattern);
+        } else {
+            s += &format!("( {}) * {} ", hex(pattern), self.len / pattern.len());
+        }
+        s
+    }
+}
+
+impl Row for Match {
+    const HEADER: &'static [&'static str] = &["pattern", "offset", "len", "context"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.pattern.to_string(),
+            self.offset.to_string(),
+            self.len.to_string(),
+            output::to_hex(&self.context),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single() {
+        let mut p = [Pattern::new(b"cd").lookback(2)];
+        let m = scan(b"abcdef", &mut p);
+        assert_eq!(
+            m,
+            [Match {
+                pattern: 0,
+                offset: 2,
+                len: 2,
+                context: b"ab".to_vec()
+            }]
+        );
+    }
+
+    #[test]
+    fn runs_are_merged() {
+        let mut p = [Pattern::new(b"\0").repeats(2), Pattern::new(b"x")];
+        let m = scan(b"a\0\0\0\0x\0\0", &mut p);
+        let m: Vec<(usize, usize, usize)> =
+            m.iter().map(|m| (m.pattern, m.offset, m.len)).collect();
+        assert_eq!(m, [(0, 1, 4), (1, 5, 1), (0, 6, 2)]);
+    }
+
+    #[test]
+    fn reader() {
+        let mut p = [Pattern::new(b"\0").repeats(2), Pattern::new(b"x")];
+        // a run split between two reads
+        let r = (&b"a\0\0"[..]).chain(&b"\0\0x\0\0"[..]);
+        let m = scan_reader(r, &mut p).unwrap();
+        let mut q = [Pattern::new(b"\0").repeats(2), Pattern::new(b"x")];
+        assert_eq!(m, scan(b"a\0\0\0\0x\0\0", &mut q));
+    }
+
+    #[test]
+    fn chunks() {
+        let patterns = || {
+            [
+                Pattern::new(b"\0").lookback(4).repeats(3),
+                Pattern::new(b"ab").lookback(3),
+                Pattern::new(b"aab"),
+            ]
+        };
+        // runs, near misses and matches every which way across chunks
+        let mut x: u32 = 1;
+        let data: Vec<u8> = (0..2000)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345);
+                [0, 0, 0, b'a', b'b', b'c'][(x >> 16) as usize % 6]
+            })
+            .collect();
+        let mut p = patterns();
+        let expected = scan(&data, &mut p);
+        for chunk in [1, 2, 3, 7, 64, 500, 1999] {
+            let mut q = patterns();
+            assert_eq!(
+                scan_chunks(&data, &mut q, chunk),
+                expected,
+                "chunk {}",
+                chunk
+            );
+            assert!(q == p);
+        }
+    }
+}
@@ -0,0 +1,724 @@
+use crate::ihex;
+use crate::mpfs;
+use crate::output::Row;
+use crate::pattern::Pattern;
+use crate::scan;
+use flate2::bufread::{GzDecoder, ZlibDecoder};
+use serde::Serialize;
+use std::io::{self, Read};
+
+/// how far we look for the end of an HTML document
+const HTML_MAX: usize = 4 * 1024 * 1024;
+/// the most `decompress` inflates, past that it's a bomb rather than firmware
+pub const DECOMPRESS_MAX: u64 = 256 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    Gzip,
+    Zlib,
+    Elf,
+    IntelHex,
+    Mpfs,
+    Png,
+    Gif,
+    Jpeg,
+    Html,
+    Pic32Boot,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Gzip => "gzip",
+            Kind::Zlib => "zlib",
+            Kind::Elf => "elf",
+            Kind::IntelHex => "intel-hex",
+            Kind::Mpfs => "mpfs",
+            Kind::Png => "png",
+            Kind::Gif => "gif",
+            Kind::Jpeg => "jpeg",
+            Kind::Html => "html",
+            Kind::Pic32Boot => "pic32-boot",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Kind::Gzip => "gz",
+            Kind::Zlib => "zlib",
+            Kind::Elf => "elf",
+            Kind::IntelHex => "hex",
+            Kind::Mpfs => "mpfs",
+            Kind::Png => "png",
+            Kind::Gif => "gif",
+            Kind::Jpeg => "jpg",
+            Kind::Html => "html",
+            Kind::Pic32Boot => "bin",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Hit {
+    pub offset: usize,
+    pub kind: Kind,
+    /// `None` when the format doesn't tell where it ends
+    pub size: Option<usize>,
+    /// 0..1, how much the header parser believes in it
+    pub confidence: f64,
+    pub description: String,
+}
+
+impl Hit {
+    pub fn data<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        match self.size {
+            Some(size) => &data[self.offset..(self.offset + size)],
+            None => &data[self.offset..],
+        }
+    }
+}
+
+impl Row for Hit {
+    const HEADER: &'static [&'static str] =
+        &["offset", "kind", "size", "confidence", "description"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.offset.to_string(),
+            self.kind.name().to_string(),
+            self.size.map(|s| s.to_string()).unwrap_or_default(),
+            format!("{:.2}", self.confidence),
+            self.description.clone(),
+        ]
+    }
+}
+
+/// what a header parser found: size, confidence and description
+type Found = (Option<usize>, f64, String);
+
+struct Signature {
+    kind: Kind,
+    magic: &'static [u8],
+    /// where the magic is from the start of the format
+    skip: usize,
+    /// gets the data from the start of the format on
+    check: fn(&[u8]) -> Option<Found>,
+}
+
+static SIGNATURES: &[Signature] = &[
+    Signature {
+        kind: Kind::Gzip,
+        magic: b"\x1f\x8b\x08",
+        skip: 0,
+        check: gzip,
+    },
+    Signature {
+        kind: Kind::Zlib,
+        magic: b"\x78\x01",
+        skip: 0,
+        check: zlib,
+    },
+    Signature {
+        kind: Kind::Zlib,
+        magic: b"\x78\x9c",
+        skip: 0,
+        check: zlib,
+    },
+    Signature {
+        kind: Kind::Zlib,
+        magic: b"\x78\xda",
+        skip: 0,
+        check: zlib,
+    },
+    Signature {
+        kind: Kind::Elf,
+        magic: b"\x7fELF",
+        skip: 0,
+        check: elf,
+    },
+    Signature {
+        kind: Kind::IntelHex,
+        magic: b":02000004",
+        skip: 0,
+        check: intel_hex,
+    },
+    Signature {
+        kind: Kind::IntelHex,
+        magic: b":10000000",
+        skip: 0,
+        check: intel_hex,
+    },
+    Signature {
+        kind: Kind::Mpfs,
+        magic: mpfs::MAGIC,
+        skip: 0,
+        check: mpfs_image,
+    },
+    Signature {
+        kind: Kind::Png,
+        magic: b"\x89PNG\r\n\x1a\n",
+        skip: 0,
+        check: png,
+    },
+    Signature {
+        kind: Kind::Gif,
+        magic: b"GIF87a",
+        skip: 0,
+        check: gif,
+    },
+    Signature {
+        kind: Kind::Gif,
+        magic: b"GIF89a",
+        skip: 0,
+        check: gif,
+    },
+    Signature {
+        kind: Kind::Jpeg,
+        magic: b"\xff\xd8\xff",
+        skip: 0,
+        check: jpeg,
+    },
+    Signature {
+        kind: Kind::Html,
+        magic: b"<!DOCTYPE html",
+        skip: 0,
+        check: html,
+    },
+    Signature {
+        kind: Kind::Html,
+        magic: b"<!doctype html",
+        skip: 0,
+        check: html,
+    },
+    Signature {
+        kind: Kind::Html,
+        magic: b"<html",
+        skip: 0,
+        check: html,
+    },
+    Signature {
+        kind: Kind::Html,
+        magic: b"<HTML",
+        skip: 0,
+        check: html,
+    },
+    // jr k0, after a lui/addiu pair loading k0
+    Signature {
+        kind: Kind::Pic32Boot,
+        magic: b"\x08\x00\x40\x03",
+        skip: 8,
+        check: pic32_boot,
+    },
+];
+
+fn be16(d: &[u8], i: usize) -> Option<usize> {
+    Some(u16::from_be_bytes([*d.get(i)?, *d.get(i + 1)?]) as usize)
+}
+
+fn le16(d: &[u8], i: usize) -> Option<usize> {
+    Some(u16::from_le_bytes([*d.get(i)?, *d.get(i + 1)?]) as usize)
+}
+
+fn be32(d: &[u8], i: usize) -> Option<u32> {
+    Some(u32::from_be_bytes([
+        *d.get(i)?,
+        *d.get(i + 1)?,
+        *d.get(i + 2)?,
+        *d.get(i + 3)?,
+    ]))
+}
+
+fn le32(d: &[u8], i: usize) -> Option<u32> {
+    Some(u32::from_le_bytes([
+        *d.get(i)?,
+        *d.get(i + 1)?,
+        *d.get(i + 2)?,
+        *d.get(i + 3)?,
+    ]))
+}
+
+/// inflates `dec` to find its size, at most `max + 1` bytes of it
+fn inflate<R: Read>(dec: R, max: u64) -> Option<(u64, R)> {
+    let mut dec = dec.take(max + 1);
+    let out = io::copy(&mut dec, &mut io::sink()).ok()?;
+    Some((out, dec.into_inner()))
+}
+
+fn gzip(d: &[u8]) -> Option<Found> {
+    gzip_max(d, DECOMPRESS_MAX)
+}
+
+fn gzip_max(d: &[u8], max: u64) -> Option<Found> {
+    // reserved flags must be clear
+    if d.len() < 18 || d[3] & 0xe0 != 0 {
+        return None;
+    }
+    let (out, dec) = inflate(GzDecoder::new(d), max)?;
+    let name = dec
+        .header()
+        .and_then(|h| h.filename())
+        .map(|n| format!(" \"{}\"", String::from_utf8_lossy(n)))
+        .unwrap_or_default();
+    // too big to be firmware, or a bomb, we don't know where it ends
+    if out > max {
+        return Some((
+            None,
+            0.5,
+            format!("gzip{}, over {} bytes uncompressed", name, max),
+        ));
+    }
+    let size = d.len() - dec.into_inner().len();
+    Some((
+        Some(size),
+        1.0,
+        format!("gzip{}, {} bytes uncompressed", name, out),
+    ))
+}
+
+fn zlib(d: &[u8]) -> Option<Found> {
+    zlib_max(d, DECOMPRESS_MAX)
+}
+
+fn zlib_max(d: &[u8], max: u64) -> Option<Found> {
+    if d.len() < 6 || !(d[0] as usize * 256 + d[1] as usize).is_multiple_of(31) || d[1] & 0x20 != 0
+    {
+        return None;
+    }
+    let (out, dec) = inflate(ZlibDecoder::new(d), max)?;
+    // an empty stream is way too easy to hit by chance
+    if out == 0 {
+        return None;
+    }
+    if out > max {
+        return Some((None, 0.5, format!("zlib, over {} bytes uncompressed", max)));
+    }
+    let size = d.len() - dec.into_inner().len();
+    Some((Some(size), 0.9, format!("zlib, {} bytes uncompressed", out)))
+}
+
+fn elf(d: &[u8]) -> Option<Found> {
+    let (class, data) = (*d.get(4)?, *d.get(5)?);
+    if !(1..=2).contains(&class) || !(1..=2).contains(&data) || d.get(6) != Some(&1) {
+        return None;
+    }
+    let u16_at = |i| if data == 1 { le16(d, i) } else { be16(d, i) };
+    let u32_at = |i| if data == 1 { le32(d, i) } else { be32(d, i) };
+
+    let machine = u16_at(18)?;
+    let (shoff, shentsize, shnum) = if class == 1 {
+        (u32_at(0x20)? as usize, u16_at(0x2e)?, u16_at(0x30)?)
+    } else {
+        let lo = u32_at(0x28)? as usize;
+        let hi = u32_at(0x2c)? as usize;
+        (lo | hi << 32, u16_at(0x3a)?, u16_at(0x3c)?)
+    };
+    // a crafted header can point anywhere
+    let size = match shoff {
+        0 => None,
+        o => Some(o.checked_add(shentsize.checked_mul(shnum)?)?),
+    };
+
+    let machine = match machine {
+        3 => "x86",
+        8 => "MIPS",
+        40 => "ARM",
+        62 => "x86-64",
+        83 => "AVR",
+        183 => "AArch64",
+        _ => "unknown machine",
+    };
+    Some((
+        size,
+        0.9,
+        format!(
+            "ELF {}-bit {}, {}",
+            if class == 1 { 32 } else { 64 },
+            if data == 1 { "LSB" } else { "MSB" },
+            machine
+        ),
+    ))
+}
+
+fn intel_hex(d: &[u8]) -> Option<Found> {
+    let (image, size) = ihex::Image::parse_prefix(d).ok()?;
+    if image.segments.is_empty() {
+        return None;
+    }
+    let len: usize = image.segments.iter().map(|s| s.data.len()).sum();
+    Some((
+        Some(size),
+        1.0,
+        format!(
+            "Intel HEX, {} segments, {} bytes",
+            image.segments.len(),
+            len
+        ),
+    ))
+}
+
+fn mpfs_image(d: &[u8]) -> Option<Found> {
+    let (header, files) = mpfs::parse(d).ok()?;
+    if files.is_empty() {
+        return None;
+    }
+    let mut size = mpfs::Header::SIZE + files.len() * (2 + mpfs::FileHeader::SIZE);
+    for f in files.iter() {
+        f.data(d).ok()?;
+        size = size.max((f.start() + f.size()) as usize);
+    }
+    Some((
+        Some(size),
+        0.9,
+        format!("MPFS {}, {} files", header.ver, files.len()),
+    ))
+}
+
+fn png(d: &[u8]) -> Option<Found> {
+    if d.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let desc = format!("PNG {}x{}", be32(d, 16)?, be32(d, 20)?);
+    let mut pos = 8;
+    while let (Some(len), Some(kind)) = (be32(d, pos), d.get((pos + 4)..(pos + 8))) {
+        pos += 12 + len as usize;
+        if kind == b"IEND" {
+            return Some((Some(pos), 1.0, desc));
+        }
+    }
+    Some((None, 0.5, desc + " (truncated)"))
+}
+
+/// skips GIF data sub-blocks, returns where the terminator ends
+fn gif_blocks(d: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let n = *d.get(pos)? as usize;
+        pos += 1 + n;
+        if n == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+fn gif_end(d: &[u8]) -> Option<usize> {
+    let table = |flags: u8| {
+        if flags & 0x80 != 0 {
+            3 << ((flags & 7) + 1)
+        } else {
+            0
+        }
+    };
+    let mut pos = 13 + table(*d.get(10)?);
+    loop {
+        match *d.get(pos)? {
+            0x2c => {
+                pos += 10 + table(*d.get(pos + 9)?);
+                pos = gif_blocks(d, pos + 1)?;
+            }
+            0x21 => pos = gif_blocks(d, pos + 2)?,
+            0x3b => return Some(pos + 1),
+            _ => return None,
+        }
+    }
+}
+
+fn gif(d: &[u8]) -> Option<Found> {
+    let (w, h) = (le16(d, 6)?, le16(d, 8)?);
+    if w == 0 || h == 0 {
+        return None;
+    }
+    let desc = format!("GIF {}x{}", w, h);
+    Some(match gif_end(d) {
+        Some(size) => (Some(size), 1.0, desc),
+        None => (None, 0.5, desc + " (truncated)"),
+    })
+}
+
+fn jpeg_end(d: &[u8]) -> Option<usize> {
+    let mut pos = 2;
+    loop {
+        if *d.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *d.get(pos + 1)?;
+        match marker {
+            0xff => pos += 1,
+            0xd9 => return Some(pos + 2),
+            0x01 | 0xd0..=0xd7 => pos += 2,
+            _ => {
+                pos += 2 + be16(d, pos + 2)?;
+                if marker == 0xda {
+                    // entropy coded data, runs until a marker that isn't
+                    // stuffing or a restart
+                    while d.get(pos)? != &0xff || matches!(d.get(pos + 1)?, 0x00 | 0xd0..=0xd7) {
+                        pos += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn jpeg(d: &[u8]) -> Option<Found> {
+    Some(match jpeg_end(d) {
+        Some(size) => (Some(size), 1.0, "JPEG".to_string()),
+        None => (None, 0.3, "JPEG (truncated)".to_string()),
+    })
+}
+
+fn html(d: &[u8]) -> Option<Found> {
+    // the document stops at the first NUL, it's text after all
+    let window = &d[..d.len().min(HTML_MAX)];
+    let text = match window.iter().position(|c| *c == 0) {
+        Some(nul) => &window[..nul],
+        None => window,
+    };
+    let end = text
+        .windows(7)
+        .position(|w| w.eq_ignore_ascii_case(b"</html>"));
+    let head = &text[..end.map_or(text.len(), |e| e + 7).min(512)];
+    if !head
+        .iter()
+        .all(|c| c.is_ascii_graphic() || c.is_ascii_whitespace() || *c >= 0x80)
+    {
+        return None;
+    }
+    Some(match end {
+        Some(end) => (Some(end + 7), 0.9, "HTML document".to_string()),
+        None => (None, 0.5, "HTML document (no end tag)".to_string()),
+    })
+}
+
+fn pic32_boot(d: &[u8]) -> Option<Found> {
+    let (lui, add) = (le32(d, 0)?, le32(d, 4)?);
+    if lui >> 16 != 0x3c1a || (add >> 16 != 0x275a && add >> 16 != 0x375a) {
+        return None;
+    }
+    let low = if add >> 16 == 0x275a {
+        // addiu sign extends
+        add as u16 as i16 as i32 as u32
+    } else {
+        add & 0xffff
+    };
+    let target = (lui << 16).wrapping_add(low);
+    Some((
+        None,
+        0.7,
+        format!("PIC32 boot stub, jumps to {:#010x}", target),
+    ))
+}
+
+fn check(sig: &Signature, data: &[u8], offset: usize) -> Option<Hit> {
+    let (size, confidence, description) = (sig.check)(&data[offset..])?;
+    Some(Hit {
+        offset,
+        kind: sig.kind,
+        size: size.filter(|s| offset.checked_add(*s).is_some_and(|e| e <= data.len())),
+        confidence,
+        description,
+    })
+}
+
+/// not in the middle of some text
+fn line_start(data: &[u8], offset: usize) -> bool {
+    offset == 0 || !data[offset - 1].is_ascii_graphic()
+}
+
+/// Looks for every signature of the catalogue in `data` and keeps the ones
+/// their header parser agrees with.
+pub fn scan(data: &[u8]) -> Vec<Hit> {
+    let mut patterns: Vec<Pattern> = SIGNATURES.iter().map(|s| Pattern::new(s.magic)).collect();
+    // the records of a HEX file match its magics again, they're part of it
+    let mut hex_end = 0;
+    let mut hits: Vec<Hit> = scan::scan(data, &mut patterns)
+        .iter()
+        .filter_map(|m| {
+            let sig = &SIGNATURES[m.pattern];
+            let offset = m.offset.checked_sub(sig.skip)?;
+            if sig.kind == Kind::IntelHex && (offset < hex_end || !line_start(data, offset)) {
+                return None;
+            }
+            let hit = check(sig, data, offset)?;
+            if hit.kind == Kind::IntelHex {
+                hex_end = offset + hit.size.unwrap_or(0);
+            }
+            Some(hit)
+        })
+        .collect();
+    hits.sort_by_key(|h| (h.offset, h.kind));
+    hits
+}
+
+/// What `data` is, if it starts with something we know.
+pub fn identify(data: &[u8]) -> Option<Hit> {
+    SIGNATURES
+        .iter()
+        .filter(|s| data.get(s.skip..(s.skip + s.magic.len())) == Some(s.magic))
+        .filter_map(|s| check(s, data, 0))
+        .next()
+}
+
+/// Uncompressed content of a gzip or zlib hit, `None` when it inflates to
+/// more than `DECOMPRESS_MAX`.
+pub fn decompress(kind: Kind, data: &[u8]) -> Option<Vec<u8>> {
+    decompress_max(kind, data, DECOMPRESS_MAX)
+}
+
+fn decompress_max(kind: Kind, data: &[u8], max: u64) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    // one more byte tells us whether there was more
+    match kind {
+        Kind::Gzip => GzDecoder::new(data)
+            .take(max + 1)
+            .read_to_end(&mut out)
+            .ok()?,
+        Kind::Zlib => ZlibDecoder::new(data)
+            .take(max + 1)
+            .read_to_end(&mut out)
+            .ok()?,
+        _ => return None,
+    };
+    match out.len() as u64 > max {
+        true => None,
+        false => Some(out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output;
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn gz(data: &[u8]) -> Vec<u8> {
+        let mut e = GzEncoder::new(Vec::new(), Compression::default());
+        e.write_all(data).unwrap();
+        e.finish().unwrap()
+    }
+
+    #[test]
+    fn compressed() {
+        let mut data = b"junk \x1f\x8b\x08 junk".to_vec();
+        let start = data.len();
+        let g = gz(b"hello hello hello");
+        data.extend(&g);
+        let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+        z.write_all(b"zlib data").unwrap();
+        let z = z.finish().unwrap();
+        data.extend(&z);
+        data.extend(b"\x78\x9c not zlib");
+
+        let hits = scan(&data);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].offset, start);
+        assert_eq!(hits[0].kind, Kind::Gzip);
+        assert_eq!(hits[0].size, Some(g.len()));
+        assert_eq!(hits[1].offset, start + g.len());
+        assert_eq!(hits[1].kind, Kind::Zlib);
+        assert_eq!(hits[1].size, Some(z.len()));
+
+        assert_eq!(
+            decompress(Kind::Gzip, hits[0].data(&data)).unwrap(),
+            b"hello hello hello"
+        );
+        assert_eq!(decompress_max(Kind::Gzip, &g, 17).unwrap().len(), 17);
+        assert_eq!(decompress_max(Kind::Gzip, &g, 16), None);
+
+        assert_eq!(gzip_max(&g, 17).unwrap().0, Some(g.len()));
+        let (size, _, description) = gzip_max(&g, 16).unwrap();
+        assert_eq!(size, None);
+        assert_eq!(description, "gzip, over 16 bytes uncompressed");
+        assert_eq!(zlib_max(&z, 9).unwrap().0, Some(z.len()));
+        assert_eq!(zlib_max(&z, 8).unwrap().0, None);
+    }
+
+    #[test]
+    fn images() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x02\0\0\0\x03".to_vec();
+        png.extend(&[0; 9]);
+        png.extend(b"\0\0\0\0IEND\xae\x42\x60\x82");
+        let gif = b"GIF89a\x02\0\x03\0\0\0\0\x2c\0\0\0\0\x02\0\x03\0\0\x02\x01\0\0\x3b";
+        let jpeg = b"\xff\xd8\xff\xe0\0\x04ab\xff\xda\0\x02\x12\xff\x00\x34\xff\xd9";
+
+        let mut data = png.clone();
+        data.extend(&gif[..]);
+        data.extend(&jpeg[..]);
+        let hits = scan(&data);
+        let h: Vec<(usize, Kind, Option<usize>)> =
+            hits.iter().map(|h| (h.offset, h.kind, h.size)).collect();
+        assert_eq!(
+            h,
+            [
+                (0, Kind::Png, Some(png.len())),
+                (png.len(), Kind::Gif, Some(gif.len())),
+                (png.len() + gif.len(), Kind::Jpeg, Some(jpeg.len())),
+            ]
+        );
+        assert_eq!(hits[0].description, "PNG 2x3");
+    }
+
+    #[test]
+    fn text() {
+        let data =
+            b"\0\0<html><body>hi</body></HTML>\0\0:020000041fc01b\n:0400000001020304f2\n:00000001FF\n\0";
+        let h: Vec<(usize, Kind, Option<usize>)> = scan(data)
+            .iter()
+            .map(|h| (h.offset, h.kind, h.size))
+            .collect();
+        assert_eq!(
+            h,
+            [(2, Kind::Html, Some(28)), (32, Kind::IntelHex, Some(48))]
+        );
+    }
+
+    #[test]
+    fn hex_records() {
+        let record = |addr: u16, kind: u8, data: &[u8]| {
+            let mut b = vec![data.len() as u8, (addr >> 8) as u8, addr as u8, kind];
+            b.extend_from_slice(data);
+            let sum = b.iter().fold(0u8, |s, c| s.wrapping_add(*c));
+            b.push(sum.wrapping_neg());
+            format!(":{}\n", output::to_hex(&b).to_uppercase())
+        };
+        let mut hex = String::new();
+        for segment in 0..3u8 {
+            hex += &record(0, 4, &[0x1f, 0xc0 + segment]);
+            hex += &record(0, 0, &[segment; 16]);
+            hex += &record(0x10, 0, &[segment; 16]);
+        }
+        hex += ":00000001FF\n";
+        let mut data = b"\0\0".to_vec();
+        data.extend(hex.as_bytes());
+
+        let h: Vec<(usize, Kind, Option<usize>)> = scan(&data)
+            .iter()
+            .map(|h| (h.offset, h.kind, h.size))
+            .collect();
+        assert_eq!(h, [(2, Kind::IntelHex, Some(hex.len()))]);
+    }
+
+    #[test]
+    fn pic32() {
+        let data = [
+            0x00, 0x9d, 0x1a, 0x3c, 0x00, 0x10, 0x5a, 0x27, 0x08, 0x00, 0x40, 0x03, 0, 0, 0, 0,
+        ];
+        let hit = identify(&data).unwrap();
+        assert_eq!(hit.kind, Kind::Pic32Boot);
+        assert_eq!(hit.description, "PIC32 boot stub, jumps to 0x9d001000");
+        assert_eq!(identify(b"\x7fELF\x03"), None);
+    }
+
+    #[test]
+    fn elf_overflow() {
+        let mut elf = b"\x7fELF\x02\x01\x01".to_vec();
+        elf.resize(0x40, 0);
+        elf[18] = 8;
+        elf[0x28..0x30].copy_from_slice(&0xffff_ffff_ffff_ff00u64.to_le_bytes());
+        elf[0x3a] = 0x40;
+        elf[0x3c] = 0x10;
+        assert_eq!(identify(&elf), None);
+        assert_eq!(scan(&elf), []);
+    }
+}
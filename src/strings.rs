@@ -0,0 +1,302 @@
+use crate::cut;
+use crate::mpfs;
+use crate::output::Row;
+use serde::Serialize;
+
+/// same default as `strings(1)`
+pub const DEFAULT_MIN_LEN: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Ascii,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub const ALL: &'static [Encoding] = &[
+        Encoding::Ascii,
+        Encoding::Utf8,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Ascii => "ascii",
+            Encoding::Utf8 => "utf8",
+            Encoding::Utf16Le => "utf16le",
+            Encoding::Utf16Be => "utf16be",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Encoding::ALL.iter().find(|e| e.name() == name).cloned()
+    }
+}
+
+/// A string found in the data, `len` is in bytes and `member` / `file` say
+/// which archive member and MPFS file it sits in, when there's one.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Str {
+    pub offset: usize,
+    pub len: usize,
+    pub encoding: Encoding,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+impl Row for Str {
+    const HEADER: &'static [&'static str] =
+        &["offset", "len", "encoding", "member", "mpfs_file", "value"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.offset.to_string(),
+            self.len.to_string(),
+            self.encoding.name().to_string(),
+            self.member.clone().unwrap_or_default(),
+            self.file.clone().unwrap_or_default(),
+            self.value.clone(),
+        ]
+    }
+}
+
+fn printable(c: char) -> bool {
+    c == '\t' || !c.is_control()
+}
+
+/// the char at the start of `data` if it's valid UTF-8, and its length
+fn utf8_char(data: &[u8]) -> Option<(char, usize)> {
+    let n = match data.first()? {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => return None,
+    };
+    let s = std::str::from_utf8(data.get(..n)?).ok()?;
+    s.chars().next().map(|c| (c, n))
+}
+
+/// ASCII and UTF-8 in one go, a run is UTF-8 as soon as it holds one non
+/// ASCII char
+fn utf8(data: &[u8], min: usize, out: &mut Vec<Str>) {
+    let mut i = 0;
+    while i < data.len() {
+        let start = i;
+        let mut value = String::new();
+        let mut chars = 0;
+        while let Some((c, n)) = utf8_char(&data[i..]).filter(|(c, _)| printable(*c)) {
+            value.push(c);
+            chars += 1;
+            i += n;
+        }
+        if chars >= min {
+            let encoding = if value.is_ascii() {
+                Encoding::Ascii
+            } else {
+                Encoding::Utf8
+            };
+            out.push(Str {
+                offset: start,
+                len: i - start,
+                encoding,
+                value,
+                member: None,
+                file: None,
+            });
+        }
+        if i == start {
+            i += 1;
+        }
+    }
+}
+
+/// UTF-16 chars we take: Latin, Greek, Cyrillic and such, the rest of the
+/// BMP would turn random data and plain ASCII into endless strings
+fn utf16_printable(u: u16) -> bool {
+    u < 0x600 && printable(char::from_u32(u as u32).unwrap())
+}
+
+fn utf16(data: &[u8], min: usize, encoding: Encoding, out: &mut Vec<Str>) {
+    let unit = |i: usize| match encoding {
+        Encoding::Utf16Be => u16::from_be_bytes([data[i], data[i + 1]]),
+        _ => u16::from_le_bytes([data[i], data[i + 1]]),
+    };
+    let mut found: Vec<Str> = Vec::new();
+    for align in 0..2 {
+        let mut i = align;
+        while i + 1 < data.len() {
+            let start = i;
+            let mut units = Vec::new();
+            while i + 1 < data.len() && utf16_printable(unit(i)) {
+                units.push(unit(i));
+                i += 2;
+            }
+            if units.len() >= min {
+                found.push(Str {
+                    offset: start,
+                    len: i - start,
+                    encoding,
+                    value: String::from_utf16_lossy(&units),
+                    member: None,
+                    file: None,
+                });
+            }
+            if i == start {
+                i += 2;
+            }
+        }
+    }
+    out.extend(found);
+}
+
+/// Finds the strings of at least `min` chars in `data`, in the given
+/// encodings, sorted by offset.
+///
+/// Latin text in UTF-16LE also reads as UTF-16BE one byte further (and the
+/// other way around), when two UTF-16 strings overlap we keep the one that
+/// starts first.
+pub fn find(data: &[u8], min: usize, encodings: &[Encoding]) -> Vec<Str> {
+    let min = min.max(1);
+    let mut out = Vec::new();
+    if encodings.contains(&Encoding::Ascii) || encodings.contains(&Encoding::Utf8) {
+        utf8(data, min, &mut out);
+        out.retain(|s| encodings.contains(&s.encoding));
+    }
+    let mut wide = Vec::new();
+    for e in [Encoding::Utf16Le, Encoding::Utf16Be] {
+        if encodings.contains(&e) {
+            utf16(data, min, e, &mut wide);
+        }
+    }
+    wide.sort_by_key(|s| (s.offset, std::cmp::Reverse(s.len)));
+    let mut end = 0;
+    for s in wide {
+        if s.offset >= end {
+            end = s.offset + s.len;
+            out.push(s);
+        }
+    }
+    out.sort_by_key(|s| s.offset);
+    out
+}
+
+/// Where the archive members and the MPFS files inside them are, to tell
+/// which one a string belongs to.
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    /// name, start and end
+    members: Vec<(String, usize, usize)>,
+    files: Vec<(String, usize, usize)>,
+}
+
+fn mpfs_files(data: &[u8], base: usize, files: &mut Vec<(String, usize, usize)>) {
+    let start = match mpfs::find(data) {
+        Some(s) => s,
+        None => return,
+    };
+    let image = &data[start..];
+    if let Ok((_, table)) = mpfs::parse(image) {
+        for f in table.iter().filter(|f| f.data(image).is_ok()) {
+            let offset = base + start + f.start() as usize;
+            files.push((f.name(), offset, offset + f.size() as usize));
+        }
+    }
+}
+
+fn holding(ranges: &[(String, usize, usize)], offset: usize) -> Option<&str> {
+    ranges
+        .iter()
+        .find(|(_, start, end)| (*start..*end).contains(&offset))
+        .map(|(name, _, _)| name.as_str())
+}
+
+impl Layout {
+    /// `data` being a `firmware.ar`, a single member or an MPFS image
+    pub fn new(data: &[u8]) -> Self {
+        let mut layout = Layout::default();
+        let members = cut::split(data).unwrap_or_default();
+        for m in members.iter() {
+            layout
+                .members
+                .push((m.path.clone(), m.offset, m.offset + m.len));
+            mpfs_files(m.data(data), m.offset, &mut layout.files);
+        }
+        if members.is_empty() {
+            mpfs_files(data, 0, &mut layout.files);
+        }
+        layout
+    }
+
+    /// the member and MPFS file holding `offset`
+    pub fn owner(&self, offset: usize) -> (Option<&str>, Option<&str>) {
+        (holding(&self.members, offset), holding(&self.files, offset))
+    }
+
+    /// fills `member` and `file` in
+    pub fn attribute(&self, strings: &mut [Str]) {
+        for s in strings.iter_mut() {
+            let (member, file) = self.owner(s.offset);
+            s.member = member.map(String::from);
+            s.file = file.map(String::from);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(strings: &[Str]) -> Vec<(usize, Encoding, &str)> {
+        strings
+            .iter()
+            .map(|s| (s.offset, s.encoding, s.value.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn encodings() {
+        let mut data = b"\x01hello\x00ab\x00caf\xc3\xa9s\xff".to_vec();
+        data.extend(b"w\0i\0d\0e\0\0\0");
+        data.extend(b"\0b\0i\0g\0!\0\0");
+        let s = find(&data, 4, Encoding::ALL);
+        assert_eq!(
+            values(&s),
+            [
+                (1, Encoding::Ascii, "hello"),
+                (10, Encoding::Utf8, "cafés"),
+                (17, Encoding::Utf16Le, "wide"),
+                (27, Encoding::Utf16Be, "big!"),
+            ]
+        );
+        assert_eq!(s[1].len, 6);
+        assert_eq!(s[2].len, 8);
+
+        let s = find(&data, 5, &[Encoding::Ascii]);
+        assert_eq!(values(&s), [(1, Encoding::Ascii, "hello")]);
+    }
+
+    #[test]
+    fn owners() {
+        let ar = cut::archive(&[("web.bin", &mpfs::image(&[("html", b"hello there")]))]);
+        let base = 2 + "web.bin".len() + 300;
+
+        let layout = Layout::new(&ar);
+        assert_eq!(layout.owner(0), (None, None));
+        assert_eq!(layout.owner(base), (Some("web.bin"), None));
+        assert_eq!(layout.owner(base + 0x20), (Some("web.bin"), Some("html")));
+
+        let mut s = find(&ar, 4, &[Encoding::Ascii]);
+        layout.attribute(&mut s);
+        let last = s.last().unwrap();
+        assert_eq!(last.value, "hello there");
+        assert_eq!(last.file.as_deref(), Some("html"));
+    }
+}
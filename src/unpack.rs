@@ -0,0 +1,272 @@
+use crate::cut;
+use crate::error::Result;
+use crate::ihex;
+use crate::mpfs;
+use crate::output::Row;
+use crate::signature::{self, Kind};
+use serde::Serialize;
+use std::borrow::Cow;
+
+/// how many containers deep we go, what a single gzip or zlib stream may
+/// inflate to is capped by `signature::DECOMPRESS_MAX`
+pub const MAX_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    /// the input itself, when it isn't an archive
+    File,
+    Member,
+    IntelHex,
+    Mpfs,
+    Gzip,
+    Zlib,
+}
+
+impl Stage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::File => "file",
+            Stage::Member => "member",
+            Stage::IntelHex => "intel-hex",
+            Stage::Mpfs => "mpfs",
+            Stage::Gzip => "gzip",
+            Stage::Zlib => "zlib",
+        }
+    }
+}
+
+/// How a file came out of its parent: decoded or cut from `len` bytes at
+/// `offset` of it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Step {
+    pub stage: Stage,
+    pub name: String,
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// A file of the output tree, the children of `a/b` go in `a/b.d/`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Entry<'a> {
+    pub path: String,
+    /// from the input down to this file
+    pub chain: Vec<Step>,
+    pub size: usize,
+    /// nothing more came out of it
+    pub leaf: bool,
+    #[serde(skip)]
+    pub data: Cow<'a, [u8]>,
+}
+
+impl Entry<'_> {
+    /// the chain on one line, `member a.hex@0x10 > intel-hex a.bin@0x0`
+    pub fn provenance(&self) -> String {
+        self.chain
+            .iter()
+            .map(|s| format!("{} {}@{:#x}", s.stage.name(), s.name, s.offset))
+            .collect::<Vec<String>>()
+            .join(" > ")
+    }
+}
+
+impl Row for Entry<'_> {
+    const HEADER: &'static [&'static str] = &["path", "stage", "size", "leaf", "provenance"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.path.clone(),
+            self.chain
+                .last()
+                .map(|s| s.stage.name().to_string())
+                .unwrap_or_default(),
+            self.size.to_string(),
+            self.leaf.to_string(),
+            self.provenance(),
+        ]
+    }
+}
+
+/// keeps MPFS names (which can be anything) usable as file names
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn renamed(name: &str, from: &str, to: &str) -> String {
+    match name.strip_suffix(from) {
+        Some(stem) if !stem.is_empty() => format!("{}{}", stem, to),
+        _ => format!("{}{}", name, to),
+    }
+}
+
+/// What comes out of `data`, decoded or cut out of it.
+fn children(name: &str, data: &[u8]) -> Vec<(Step, Vec<u8>)> {
+    let step = |stage, name, offset, len| Step {
+        stage,
+        name,
+        offset,
+        len,
+    };
+    match signature::identify(data) {
+        Some(h) if h.kind == Kind::IntelHex => match ihex::Image::parse_prefix(data) {
+            Ok((image, used)) => {
+                let name = renamed(name, ".hex", ".bin");
+                return vec![(step(Stage::IntelHex, name, 0, used), image.concat())];
+            }
+            Err(_) => return Vec::new(),
+        },
+        Some(h) if h.kind == Kind::Gzip || h.kind == Kind::Zlib => {
+            let stage = match h.kind {
+                Kind::Gzip => Stage::Gzip,
+                _ => Stage::Zlib,
+            };
+            let compressed = h.data(data);
+            return match signature::decompress(h.kind, compressed) {
+                Some(d) => {
+                    let name = renamed(name, &format!(".{}", h.kind.extension()), ".out");
+                    vec![(step(stage, name, 0, compressed.len()), d)]
+                }
+                None => Vec::new(),
+            };
+        }
+        _ => (),
+    }
+
+    let start = match mpfs::find(data) {
+        Some(s) => s,
+        None => return Vec::new(),
+    };
+    let image = &data[start..];
+    let files = match mpfs::parse(image) {
+        Ok((_, files)) => files,
+        Err(_) => return Vec::new(),
+    };
+    files
+        .iter()
+        .enumerate()
+        .filter_map(|(i, f)| {
+            let content = f.data(image).ok()?;
+            // names aren't unique, and not always printable
+            let name = format!("{:03}-{}", i, sanitize(&f.name()));
+            let offset = start + f.start() as usize;
+            Some((
+                step(Stage::Mpfs, name, offset, content.len()),
+                content.to_vec(),
+            ))
+        })
+        .collect()
+}
+
+fn walk<'a>(path: String, chain: Vec<Step>, data: Cow<'a, [u8]>, entries: &mut Vec<Entry<'a>>) {
+    // members have a whole path for a name
+    let name = chain
+        .last()
+        .and_then(|s| s.name.rsplit('/').next())
+        .unwrap_or_default();
+    let kids = match chain.len() < MAX_DEPTH {
+        true => children(name, &data),
+        false => Vec::new(),
+    };
+    entries.push(Entry {
+        path: path.clone(),
+        chain: chain.clone(),
+        size: data.len(),
+        leaf: kids.is_empty(),
+        data,
+    });
+    for (step, d) in kids {
+        let p = format!("{}.d/{}", path, step.name);
+        let mut c = chain.clone();
+        c.push(step);
+        walk(p, c, Cow::Owned(d), entries);
+    }
+}
+
+/// Unpacks every member of a `firmware.ar` (or `data` itself, called
+/// `name`, when it isn't one) as far as it goes, parents first.
+pub fn unpack<'a>(data: &'a [u8], name: &str) -> Result<Vec<Entry<'a>>> {
+    let mut entries = Vec::new();
+    let members = cut::split(data)?;
+    if members.is_empty() {
+        let step = Step {
+            stage: Stage::File,
+            name: sanitize(name),
+            offset: 0,
+            len: data.len(),
+        };
+        walk(
+            step.name.clone(),
+            vec![step],
+            Cow::Borrowed(data),
+            &mut entries,
+        );
+    }
+    for m in members.iter() {
+        let path = m.relative_path().to_string_lossy().to_string();
+        let step = Step {
+            stage: Stage::Member,
+            name: m.path.clone(),
+            offset: m.offset,
+            len: m.len,
+        };
+        walk(path, vec![step], Cow::Borrowed(m.data(data)), &mut entries);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// an MPFS image holding a gzipped and a plain file
+    fn mpfs_image() -> Vec<u8> {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(b"<html>hi</html>").unwrap();
+        let gz = gz.finish().unwrap();
+        mpfs::image(&[("x.gz", &gz), (".txt", b"hello")])
+    }
+
+    #[test]
+    fn nested() {
+        let mut data = b"bin ".to_vec();
+        data.extend(mpfs_image());
+        let e = unpack(&data, "web.bin").unwrap();
+        let paths: Vec<&str> = e.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "web.bin",
+                "web.bin.d/000-x.gz",
+                "web.bin.d/000-x.gz.d/000-x.out",
+                "web.bin.d/001-.txt",
+            ]
+        );
+        assert!(!e[0].leaf && !e[1].leaf && e[2].leaf && e[3].leaf);
+        assert_eq!(e[2].data.as_ref(), b"<html>hi</html>");
+        assert_eq!(e[3].data.as_ref(), b"hello");
+        assert_eq!(e[1].chain[1].offset, 4 + 56);
+        assert_eq!(e[2].chain.len(), 3);
+    }
+
+    #[test]
+    fn hex_member() {
+        let hex = b":020000040000FA\n:0400000068656C6C57\n:00000001FF\n";
+        let ar = cut::archive(&[("app.hex", hex)]);
+        let e = unpack(&ar, "firmware.ar").unwrap();
+        assert_eq!(e.len(), 2);
+        assert_eq!(e[1].path, "app.hex.d/app.bin");
+        assert_eq!(e[1].data.as_ref(), b"hell");
+        assert_eq!(
+            e[1].provenance(),
+            format!("member app.hex@{:#x} > intel-hex app.bin@0x0", 309)
+        );
+    }
+}
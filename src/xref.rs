@@ -0,0 +1,242 @@
+use crate::baseaddr::{self, Endian};
+use crate::output::Row;
+use crate::strings::{self, Encoding};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// fewer consecutive pointers than this aren't a table
+pub const MIN_TABLE: usize = 3;
+/// a jump table's targets are all code of the same function, this close
+/// to each other
+pub const JUMP_SPAN: u32 = 0x1000;
+
+/// A word of the image that points into it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Pointer {
+    pub offset: usize,
+    pub target: u32,
+    /// `target` as an offset of the image
+    pub target_offset: usize,
+}
+
+impl Row for Pointer {
+    const HEADER: &'static [&'static str] = &["offset", "target", "target_offset"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.offset.to_string(),
+            format!("{:#010x}", self.target),
+            self.target_offset.to_string(),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// every entry points to a string
+    Strings,
+    /// aligned targets close to each other, a `switch`
+    Jump,
+    Pointers,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Strings => "strings",
+            Kind::Jump => "jump",
+            Kind::Pointers => "pointers",
+        }
+    }
+}
+
+/// Consecutive pointers, `len` being the number of entries.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Table {
+    pub offset: usize,
+    pub len: usize,
+    pub kind: Kind,
+}
+
+impl Row for Table {
+    const HEADER: &'static [&'static str] = &["offset", "len", "kind"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.offset.to_string(),
+            self.len.to_string(),
+            self.kind.name().to_string(),
+        ]
+    }
+}
+
+/// Every word of `data` that points into it, `data` being loaded at `base`.
+pub fn pointers(data: &[u8], base: u32, endian: Endian) -> Vec<Pointer> {
+    baseaddr::words(data, endian)
+        .filter_map(|(offset, target)| {
+            let target_offset = target.checked_sub(base)? as usize;
+            if target_offset >= data.len() {
+                return None;
+            }
+            Some(Pointer {
+                offset,
+                target,
+                target_offset,
+            })
+        })
+        .collect()
+}
+
+fn kind(entries: &[Pointer], string_starts: &HashSet<usize>) -> Kind {
+    if entries
+        .iter()
+        .all(|p| string_starts.contains(&p.target_offset))
+    {
+        return Kind::Strings;
+    }
+    let min = entries.iter().map(|p| p.target).min().unwrap_or(0);
+    let max = entries.iter().map(|p| p.target).max().unwrap_or(0);
+    if entries.iter().all(|p| p.target % 4 == 0) && max - min < JUMP_SPAN {
+        Kind::Jump
+    } else {
+        Kind::Pointers
+    }
+}
+
+/// Groups runs of at least `MIN_TABLE` pointers stored back to back.
+pub fn tables(data: &[u8], pointers: &[Pointer]) -> Vec<Table> {
+    let string_starts: HashSet<usize> =
+        strings::find(data, strings::DEFAULT_MIN_LEN, &[Encoding::Ascii])
+            .iter()
+            .map(|s| s.offset)
+            .collect();
+    let mut tables = Vec::new();
+    let mut start = 0;
+    for i in 1..=pointers.len() {
+        if i < pointers.len() && pointers[i].offset == pointers[i - 1].offset + 4 {
+            continue;
+        }
+        let entries = &pointers[start..i];
+        if entries.len() >= MIN_TABLE {
+            tables.push(Table {
+                offset: entries[0].offset,
+                len: entries.len(),
+                kind: kind(entries, &string_starts),
+            });
+        }
+        start = i;
+    }
+    tables
+}
+
+/// The pointers of an image, its tables and who points where.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Xrefs {
+    pub base: u32,
+    pub endian: Endian,
+    pub pointers: Vec<Pointer>,
+    pub tables: Vec<Table>,
+    /// target offset to the indexes of the pointers to it
+    #[serde(skip)]
+    index: BTreeMap<usize, Vec<usize>>,
+}
+
+impl Xrefs {
+    pub fn new(data: &[u8], base: u32, endian: Endian) -> Self {
+        let pointers = pointers(data, base, endian);
+        let tables = tables(data, &pointers);
+        let mut index: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (i, p) in pointers.iter().enumerate() {
+            index.entry(p.target_offset).or_default().push(i);
+        }
+        Xrefs {
+            base,
+            endian,
+            pointers,
+            tables,
+            index,
+        }
+    }
+
+    /// the pointers to `offset`
+    pub fn to(&self, offset: usize) -> Vec<&Pointer> {
+        self.index
+            .get(&offset)
+            .into_iter()
+            .flatten()
+            .map(|i| &self.pointers[*i])
+            .collect()
+    }
+
+    /// `address` as an offset of the image, if it's in there
+    pub fn offset(&self, address: u32, len: usize) -> Option<usize> {
+        let o = address.checked_sub(self.base)? as usize;
+        if o < len {
+            Some(o)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: u32 = 0x9d00_0000;
+
+    fn image() -> Vec<u8> {
+        let mut data = b"\0first\0second\0third\0".to_vec();
+        data.resize(0x40, 0);
+        // a string table
+        for s in [1u32, 7, 14] {
+            data.extend_from_slice(&(BASE + s).to_le_bytes());
+        }
+        data.extend_from_slice(&[0; 4]);
+        // a jump table
+        for t in [0x100u32, 0x120, 0x140, 0x100] {
+            data.extend_from_slice(&(BASE + t).to_le_bytes());
+        }
+        data.extend_from_slice(&[0; 4]);
+        // a lone pointer and one outside the image
+        data.extend_from_slice(&(BASE + 7).to_le_bytes());
+        data.extend_from_slice(&(BASE + 0x10000).to_le_bytes());
+        data.resize(0x200, 0);
+        data
+    }
+
+    #[test]
+    fn tables() {
+        let data = image();
+        let x = Xrefs::new(&data, BASE, Endian::Little);
+        assert_eq!(x.pointers.len(), 8);
+        assert_eq!(
+            x.tables,
+            [
+                Table {
+                    offset: 0x40,
+                    len: 3,
+                    kind: Kind::Strings
+                },
+                Table {
+                    offset: 0x50,
+                    len: 4,
+                    kind: Kind::Jump
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn refs() {
+        let data = image();
+        let x = Xrefs::new(&data, BASE, Endian::Little);
+        let to: Vec<usize> = x.to(7).iter().map(|p| p.offset).collect();
+        assert_eq!(to, [0x44, 0x64]);
+        assert_eq!(x.to(0x100).len(), 2);
+        assert!(x.to(2).is_empty());
+        assert_eq!(x.offset(BASE + 7, data.len()), Some(7));
+        assert_eq!(x.offset(BASE - 1, data.len()), None);
+    }
+}